@@ -0,0 +1,133 @@
+use serde_json::json;
+use sqlx::SqlitePool;
+
+use super::{AuditArgs, AuditDecision, AuditFormat};
+use crate::{
+    config, db,
+    errors::{AppError, Result},
+};
+
+pub async fn execute(args: AuditArgs) -> Result<()> {
+    // Reads only the local database, so no bot token is needed.
+    let config = config::load()?;
+
+    let pool = db::connect(&config).await?;
+    let filter = db::AuditFilter {
+        session: args.session,
+        since: resolve_timestamp(&pool, args.since.as_deref()).await?,
+        until: resolve_timestamp(&pool, args.until.as_deref()).await?,
+        decision: args.decision.map(|decision| match decision {
+            AuditDecision::Allow => "allow".to_string(),
+            AuditDecision::Deny => "deny".to_string(),
+        }),
+        limit: args
+            .limit
+            .or((args.format == AuditFormat::Table).then_some(50)),
+    };
+    let mut entries = db::list_audit_entries(&pool, &filter).await?;
+    entries.reverse();
+
+    match args.format {
+        AuditFormat::Table => {
+            if entries.is_empty() {
+                println!("No permission decisions recorded.");
+                return Ok(());
+            }
+            for entry in entries {
+                let decider = match &entry.decided_by {
+                    Some(name) => format!("{} ({name})", entry.decider),
+                    None => entry.decider.clone(),
+                };
+                println!(
+                    "{} | {} | {} | {} | {} | {}ms | {}",
+                    entry.decided_at,
+                    entry.decision,
+                    decider,
+                    entry.session_name,
+                    entry.tool_name,
+                    entry.latency_ms,
+                    entry.tool_input
+                );
+            }
+        }
+        AuditFormat::Csv => {
+            println!(
+                "request_id,session_id,session_name,tool_name,tool_input,decision,decider,decided_by,latency_ms,requested_at,decided_at"
+            );
+            for entry in entries {
+                let fields = [
+                    entry.request_id,
+                    entry.session_id,
+                    entry.session_name,
+                    entry.tool_name,
+                    entry.tool_input,
+                    entry.decision,
+                    entry.decider,
+                    entry.decided_by.unwrap_or_default(),
+                    entry.latency_ms.to_string(),
+                    entry.requested_at,
+                    entry.decided_at,
+                ];
+                let row = fields
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                println!("{row}");
+            }
+        }
+        AuditFormat::Jsonl => {
+            for entry in entries {
+                let line = json!({
+                    "request_id": entry.request_id,
+                    "session_id": entry.session_id,
+                    "session_name": entry.session_name,
+                    "tool_name": entry.tool_name,
+                    "tool_input": entry.tool_input,
+                    "decision": entry.decision,
+                    "decider": entry.decider,
+                    "decided_by": entry.decided_by,
+                    "latency_ms": entry.latency_ms,
+                    "requested_at": entry.requested_at,
+                    "decided_at": entry.decided_at,
+                });
+                println!("{line}");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn resolve_timestamp(pool: &SqlitePool, input: Option<&str>) -> Result<Option<i64>> {
+    let Some(input) = input else {
+        return Ok(None);
+    };
+    match db::parse_timestamp(pool, input).await? {
+        Some(epoch) => Ok(Some(epoch)),
+        None => Err(AppError::InvalidTimestamp(input.to_string())),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::csv_field;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("cargo test"), "cargo test");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(
+            csv_field(r#"{"command":"ls"}"#),
+            r#""{""command"":""ls""}""#
+        );
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+}
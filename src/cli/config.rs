@@ -0,0 +1,48 @@
+use super::{ConfigArgs, ConfigCommand};
+use crate::{
+    config::{self, SecretStoreKind},
+    errors::Result,
+    secrets,
+};
+
+pub fn execute(args: ConfigArgs) -> Result<()> {
+    match args.command {
+        ConfigCommand::MigrateSecrets { to } => migrate_secrets(to),
+    }
+}
+
+fn migrate_secrets(to: SecretStoreKind) -> Result<()> {
+    let mut config = config::load()?;
+    let moved = secrets::migrate(&mut config, to)?;
+    config::save(&config)?;
+
+    if moved.is_empty() {
+        println!(
+            "No plaintext tokens found; secret_store is now `{}`.",
+            to.as_str()
+        );
+    } else {
+        println!(
+            "Moved {} to the {} store; config.toml no longer holds them.",
+            moved.join(", "),
+            to.as_str()
+        );
+    }
+    match to {
+        SecretStoreKind::File => println!(
+            "Set CODELATCH_SECRETS_PASSPHRASE wherever the daemon runs; the tokens are in {}.",
+            secrets::secrets_path()?.display()
+        ),
+        SecretStoreKind::Env => println!(
+            "Keep {} exported wherever the daemon runs.",
+            moved
+                .iter()
+                .map(|name| secrets::env_var(name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        SecretStoreKind::Keyring | SecretStoreKind::Config => {}
+    }
+    println!("Restart the daemon (`codelatch stop && codelatch start`) to pick this up.");
+    Ok(())
+}
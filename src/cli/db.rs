@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use super::{DbArgs, DbCommand};
+use crate::{
+    config,
+    db::{self, migrate},
+    errors::Result,
+};
+
+pub async fn execute(args: DbArgs) -> Result<()> {
+    // Reads only the local database, so no bot token is needed.
+    let config = config::load()?;
+
+    match args.command {
+        DbCommand::Migrate(args) => {
+            println!("Database: {}", config.db_path);
+            let (migrations, current) = if args.status {
+                if !Path::new(&config.db_path).exists() {
+                    println!("Database file does not exist yet.");
+                    return Ok(());
+                }
+                let pool = db::open_read_only(&config).await?;
+                (
+                    migrate::status(&pool).await?,
+                    migrate::current_version(&pool).await?,
+                )
+            } else {
+                let pool = db::open(&config).await?;
+                migrate::run(&pool).await?;
+                (
+                    migrate::status(&pool).await?,
+                    migrate::current_version(&pool).await?,
+                )
+            };
+            println!(
+                "Schema version: {} (binary supports {})",
+                current,
+                migrate::latest_version()
+            );
+            for migration in migrations {
+                let state = match migration.applied_at {
+                    Some(applied_at) => format!("applied {applied_at}"),
+                    None => "pending".to_string(),
+                };
+                println!("{:04} {} | {}", migration.version, migration.name, state);
+            }
+        }
+    }
+    Ok(())
+}
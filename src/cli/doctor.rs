@@ -22,11 +22,44 @@ pub async fn execute(args: DoctorArgs) -> Result<()> {
     println!("Doctor checks:");
     let mut failures = Vec::new();
 
-    if plugin::hooks_installed()? {
-        println!("✅ hooks installed");
-    } else {
-        println!("❌ hooks missing");
-        failures.push("hooks not installed".to_string());
+    let reports = plugin::scope_reports()?;
+    for report in &reports {
+        if let Some(err) = &report.error {
+            println!("❌ {} settings unreadable ({err})", report.scope.label());
+            failures.push(format!("{} settings unreadable", report.scope.label()));
+        }
+    }
+    let binary_path = plugin::resolve_binary()?;
+    let modes = plugin::install_modes()?;
+    match modes.as_slice() {
+        [] => {
+            println!("❌ hooks missing");
+            failures.push("hooks not installed".to_string());
+        }
+        [mode] => println!("✅ hooks installed ({})", mode.describe()?),
+        _ => {
+            let sources = modes
+                .iter()
+                .map(|mode| mode.describe())
+                .collect::<Result<Vec<_>>>()?
+                .join("; ");
+            println!(
+                "❌ hooks installed more than once ({sources}); each event fires once per copy"
+            );
+            failures.push("hooks installed more than once".to_string());
+        }
+    }
+    for mode in &modes {
+        let drift = plugin::hook_drift(*mode, &binary_path, &config)?;
+        if drift.is_empty() {
+            continue;
+        }
+        println!(
+            "❌ {} hooks drifted from config ({}); rerun `codelatch hooks install`",
+            mode.label(),
+            drift.join(", ")
+        );
+        failures.push(format!("{} hooks drifted from config", mode.label()));
     }
 
     let tmux_ok = Command::new("tmux")
@@ -85,11 +118,12 @@ pub async fn execute(args: DoctorArgs) -> Result<()> {
         }
     }
 
-    match daemon::get_bot_username(config.token()?).await {
-        Ok(username) => println!("✅ telegram auth ok (@{username})"),
+    let transport = config.transport.label().to_ascii_lowercase();
+    match daemon::transport_identity(&config).await {
+        Ok(identity) => println!("✅ {transport} auth ok ({identity})"),
         Err(err) => {
-            println!("❌ telegram auth failed ({err})");
-            failures.push("telegram auth failed".to_string());
+            println!("❌ {transport} auth failed ({err})");
+            failures.push(format!("{transport} auth failed"));
         }
     }
 
@@ -104,11 +138,25 @@ pub async fn execute(args: DoctorArgs) -> Result<()> {
 async fn apply_fixes(config: &config::Config) -> Result<()> {
     println!("Applying safe fixes...");
 
-    if !plugin::hooks_installed()? {
-        let binary_path = std::env::current_exe()?;
-        plugin::install_hooks(&binary_path)?;
-        plugin::write_plugin_artifacts(&binary_path)?;
-        println!("✅ Reinstalled hooks");
+    let binary_path = plugin::resolve_binary()?;
+    let modes = plugin::install_modes()?;
+    if modes.is_empty() {
+        let install = plugin::install(&binary_path, plugin::HookScope::User, config, true)?;
+        println!("✅ Reinstalled hooks ({})", install.mode.describe()?);
+    }
+    for mode in modes {
+        if plugin::hook_drift(mode, &binary_path, config)?.is_empty() {
+            continue;
+        }
+        match mode {
+            plugin::InstallMode::Plugin => {
+                plugin::write_plugin_artifacts(&binary_path, config)?;
+            }
+            plugin::InstallMode::Settings(scope) => {
+                plugin::install_hooks(&binary_path, scope, config)?;
+            }
+        }
+        println!("✅ Resynced {} hooks with config", mode.label());
     }
 
     if UnixStream::connect(&config.socket_path).await.is_err() {
@@ -0,0 +1,44 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{GrantsArgs, GrantsCommand};
+use crate::{config, db, errors::Result};
+
+pub async fn execute(args: GrantsArgs) -> Result<()> {
+    // Reads only the local database, so no bot token is needed.
+    let config = config::load()?;
+
+    let pool = db::connect(&config).await?;
+    match args.command {
+        GrantsCommand::List => {
+            let grants = db::list_active_grants(&pool).await?;
+            if grants.is_empty() {
+                println!("No learned grants.");
+                return Ok(());
+            }
+            for grant in grants {
+                println!(
+                    "#{} | {}={} | {} {} {} | created={}",
+                    grant.id,
+                    grant.scope,
+                    grant.scope_value,
+                    grant.tool_name,
+                    grant.match_kind,
+                    grant.pattern,
+                    grant.created_at
+                );
+            }
+        }
+        GrantsCommand::Revoke { id } => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+            if db::revoke_grant(&pool, id, now).await? {
+                println!("Revoked grant #{id}.");
+            } else {
+                println!("Grant #{id} not found or already revoked.");
+            }
+        }
+    }
+    Ok(())
+}
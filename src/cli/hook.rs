@@ -1,4 +1,4 @@
-use std::{env, io::Read};
+use std::{collections::BTreeMap, env, io::Read};
 
 use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt};
@@ -28,13 +28,28 @@ pub async fn execute(args: HookArgs) -> Result<()> {
         serde_json::from_str(&payload_text)?
     };
 
-    let blocking = args.event == "PermissionRequest";
     let managed_session_id = env::var("CODELATCH_SESSION_ID").ok();
     let managed_session_name = env::var("CODELATCH_SESSION_NAME").ok();
+    let managed = managed_session_id.is_some() && managed_session_name.is_some();
+
+    // The gate only guards codelatch sessions, and only the tools it is
+    // matched to. Anything else passes straight through, so a stopped daemon
+    // cannot block every tool call on the machine.
+    if args.event == "PreToolUse" && config.gate.enabled {
+        let tool_name = payload
+            .get("tool_name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !managed || !config.hook_matches(&args.event, tool_name) {
+            return Ok(());
+        }
+    }
+
+    let blocking = config.is_blocking_event(&args.event);
 
     // Hooks are installed globally; skip async events from non-codelatch sessions
     // to avoid noisy "unmanaged-session" lifecycle messages in Telegram.
-    if !blocking && (managed_session_id.is_none() || managed_session_name.is_none()) {
+    if !blocking && !managed {
         return Ok(());
     }
 
@@ -42,6 +57,23 @@ pub async fn execute(args: HookArgs) -> Result<()> {
     let session_name = managed_session_name.unwrap_or_else(|| "unmanaged-session".to_string());
     let tmux_pane = env::var("TMUX_PANE").ok();
     let cwd = env::current_dir()?.display().to_string();
+    // The daemon cannot see the session's environment, so the named
+    // variables travel with SessionStart for the redactor.
+    let secrets = if args.event == "SessionStart" && config.redaction.session_secrets {
+        config
+            .redaction
+            .secret_env_vars
+            .iter()
+            .filter_map(|name| {
+                env::var(name)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (name.clone(), value))
+            })
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
 
     let envelope = HookEnvelope {
         version: 1,
@@ -53,6 +85,7 @@ pub async fn execute(args: HookArgs) -> Result<()> {
         blocking,
         cwd,
         payload,
+        secrets,
     };
 
     let stream = match UnixStream::connect(&config.socket_path).await {
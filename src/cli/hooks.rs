@@ -0,0 +1,47 @@
+use super::{HooksArgs, HooksCommand};
+use crate::{config, errors::Result, plugin};
+
+pub fn execute(args: HooksArgs) -> Result<()> {
+    match args.command {
+        HooksCommand::Install { scope, settings } => {
+            let config = config::load()?;
+            let binary_path = plugin::resolve_binary()?;
+            let install = plugin::install(&binary_path, scope, &config, !settings)?;
+            if let Some(reason) = &install.fallback_reason {
+                println!("⚠️ plugin install unavailable ({reason}); falling back to settings.json");
+            }
+            println!("Hooks installed ({})", install.mode.describe()?);
+            if let Some(backup) = install.backup {
+                println!("Previous settings backed up to {}", backup.display());
+            }
+            let others = plugin::install_modes()?
+                .into_iter()
+                .filter(|mode| *mode != install.mode)
+                .map(plugin::InstallMode::label)
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                println!(
+                    "⚠️ codelatch hooks are also installed via {}; remove them with `codelatch hooks uninstall --scope <scope>` to avoid duplicate prompts",
+                    others.join(", ")
+                );
+            }
+        }
+        HooksCommand::Uninstall { scope } => {
+            if scope == plugin::HookScope::User && plugin::plugin_enabled()? {
+                plugin::uninstall_plugin()?;
+                println!("Uninstalled Claude Code plugin {}", plugin::PLUGIN_ID);
+            }
+            let settings_path = scope.settings_path()?;
+            let removed = plugin::uninstall_hooks(scope)?;
+            if removed == 0 {
+                println!("No codelatch hooks found in {}", settings_path.display());
+            } else {
+                println!(
+                    "Removed {removed} codelatch hook(s) from {}",
+                    settings_path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -7,9 +7,15 @@ use std::{
 use tokio::{net::UnixStream, time::sleep};
 use tracing::info;
 
-use crate::{config, daemon, errors::Result, plugin};
+use super::InitArgs;
+use crate::{
+    config::{self, Config, SecretStoreKind},
+    daemon,
+    errors::Result,
+    plugin, secrets,
+};
 
-pub async fn execute() -> Result<()> {
+pub async fn execute(args: InitArgs) -> Result<()> {
     let mut config = config::load().unwrap_or_default();
 
     print!("Telegram bot token (from BotFather): ");
@@ -18,30 +24,66 @@ pub async fn execute() -> Result<()> {
     io::stdin().read_line(&mut token)?;
     let token = token.trim().to_string();
 
-    let username = daemon::get_bot_username(&token).await?;
+    let username = daemon::get_bot_username(&config.telegram_api_url, &token).await?;
     println!("Bot verified: @{username}");
     println!("Send /start to @{username} now. Waiting up to 120 seconds...");
 
-    let chat_id = daemon::wait_for_start_chat(&token, Duration::from_secs(120)).await?;
+    let chat_id =
+        daemon::wait_for_start_chat(&config.telegram_api_url, &token, Duration::from_secs(120))
+            .await?;
     println!("Paired chat_id: {chat_id}");
 
     config.telegram_bot_token = Some(token);
     config.telegram_chat_id = Some(chat_id);
+    let store = store_token(&mut config)?;
     config::save(&config)?;
 
-    let binary_path = std::env::current_exe()?;
-    plugin::install_hooks(&binary_path)?;
-    plugin::write_plugin_artifacts(&binary_path)?;
+    let binary_path = plugin::resolve_binary()?;
+    let install = plugin::install(&binary_path, args.scope, &config, !args.settings)?;
+    if let Some(reason) = &install.fallback_reason {
+        println!("Plugin install unavailable ({reason}); using settings.json ⚠️");
+    }
     let daemon_ready = ensure_daemon_running(&config.socket_path).await;
 
     info!("init completed");
-    print_init_summary(daemon_ready.is_ok());
+    print_init_summary(&install.mode.describe()?, daemon_ready.is_ok());
+    match store {
+        SecretStoreKind::Config => println!(
+            "Bot token stored in plaintext config.toml ⚠️ (run `codelatch config migrate-secrets --to file` with CODELATCH_SECRETS_PASSPHRASE set)"
+        ),
+        store => println!("Bot token stored in the {} store ✅", store.as_str()),
+    }
     Ok(())
 }
 
-fn print_init_summary(daemon_ready: bool) {
+// An explicitly chosen store is used as is. Otherwise the token goes to the
+// OS keyring when one is reachable, then to the encrypted file when a
+// passphrase is set, and only as a last resort stays in config.toml.
+fn store_token(config: &mut Config) -> Result<SecretStoreKind> {
+    if config.secret_store != SecretStoreKind::Config {
+        secrets::migrate(config, config.secret_store)?;
+        return Ok(config.secret_store);
+    }
+    let mut candidates = vec![SecretStoreKind::Keyring];
+    if std::env::var_os("CODELATCH_SECRETS_PASSPHRASE").is_some() {
+        candidates.push(SecretStoreKind::File);
+    }
+    for store in candidates {
+        let mut attempt = config.clone();
+        match secrets::migrate(&mut attempt, store) {
+            Ok(_) => {
+                *config = attempt;
+                return Ok(store);
+            }
+            Err(err) => info!(store = store.as_str(), error = %err, "secret store unavailable"),
+        }
+    }
+    Ok(SecretStoreKind::Config)
+}
+
+fn print_init_summary(hooks: &str, daemon_ready: bool) {
     println!("Paired ✅");
-    println!("Hooks installed ✅");
+    println!("Hooks installed ({hooks}) ✅");
     if daemon_ready {
         println!("Daemon running ✅");
     } else {
@@ -1,6 +1,12 @@
+mod audit;
+mod config;
+mod db;
 mod doctor;
+mod grants;
 mod hook;
+mod hooks;
 mod init;
+mod redact;
 mod run;
 mod service;
 mod sessions;
@@ -8,10 +14,12 @@ mod start;
 mod status;
 mod stop;
 
-use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use tracing::info;
 
-use crate::errors::Result;
+use crate::{config::SecretStoreKind, errors::Result, plugin::HookScope};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -27,14 +35,20 @@ pub struct Cli {
 #[derive(Debug, Subcommand)]
 pub enum Command {
     Run(RunArgs),
-    Init,
+    Init(InitArgs),
     Start(StartArgs),
     Stop,
     Status,
     Doctor(DoctorArgs),
     Hook(HookArgs),
-    Sessions,
+    Sessions(SessionsArgs),
     Service(ServiceArgs),
+    Audit(AuditArgs),
+    Grants(GrantsArgs),
+    Hooks(HooksArgs),
+    Db(DbArgs),
+    Redact(RedactArgs),
+    Config(ConfigArgs),
 }
 
 #[derive(Debug, Args, Default, Clone)]
@@ -47,6 +61,20 @@ pub struct RunArgs {
     pub claude_args: Vec<String>,
 }
 
+#[derive(Debug, Args, Clone)]
+pub struct SessionsArgs {
+    #[arg(long, default_value_t = false)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args, Clone, Default)]
+pub struct InitArgs {
+    #[arg(long, value_enum, default_value_t = HookScope::User)]
+    pub scope: HookScope,
+    #[arg(long, default_value_t = false)]
+    pub settings: bool,
+}
+
 #[derive(Debug, Args, Clone)]
 pub struct HookArgs {
     pub event: String,
@@ -79,18 +107,122 @@ pub enum ServiceCommand {
     Status,
 }
 
+#[derive(Debug, Args, Clone)]
+pub struct AuditArgs {
+    #[arg(long)]
+    pub session: Option<String>,
+    #[arg(long)]
+    pub since: Option<String>,
+    #[arg(long)]
+    pub until: Option<String>,
+    #[arg(long, value_enum)]
+    pub decision: Option<AuditDecision>,
+    #[arg(long, value_enum, default_value_t = AuditFormat::Table)]
+    pub format: AuditFormat,
+    #[arg(long)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum AuditDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuditFormat {
+    Table,
+    Csv,
+    Jsonl,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct GrantsArgs {
+    #[command(subcommand)]
+    pub command: GrantsCommand,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum GrantsCommand {
+    List,
+    Revoke { id: i64 },
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct HooksArgs {
+    #[command(subcommand)]
+    pub command: HooksCommand,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum HooksCommand {
+    Install {
+        #[arg(long, value_enum, default_value_t = HookScope::User)]
+        scope: HookScope,
+        #[arg(long, default_value_t = false)]
+        settings: bool,
+    },
+    Uninstall {
+        #[arg(long, value_enum, default_value_t = HookScope::User)]
+        scope: HookScope,
+    },
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct DbArgs {
+    #[command(subcommand)]
+    pub command: DbCommand,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum DbCommand {
+    Migrate(MigrateArgs),
+}
+
+#[derive(Debug, Args, Clone, Default)]
+pub struct MigrateArgs {
+    #[arg(long, default_value_t = false)]
+    pub status: bool,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum ConfigCommand {
+    MigrateSecrets {
+        #[arg(long, value_enum, default_value_t = SecretStoreKind::Keyring)]
+        to: SecretStoreKind,
+    },
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct RedactArgs {
+    #[arg(long, value_name = "FILE")]
+    pub test: PathBuf,
+}
+
 pub async fn dispatch() -> Result<()> {
     let cli = Cli::parse();
     match cli.command.unwrap_or(Command::Run(RunArgs::default())) {
         Command::Run(args) => run::execute(args).await?,
-        Command::Init => init::execute().await?,
+        Command::Init(args) => init::execute(args).await?,
         Command::Start(args) => start::execute(args).await?,
         Command::Stop => stop::execute().await?,
         Command::Status => status::execute().await?,
         Command::Doctor(args) => doctor::execute(args).await?,
         Command::Hook(args) => hook::execute(args).await?,
-        Command::Sessions => sessions::execute().await?,
+        Command::Sessions(args) => sessions::execute(args).await?,
         Command::Service(args) => service::execute(args).await?,
+        Command::Audit(args) => audit::execute(args).await?,
+        Command::Grants(args) => grants::execute(args).await?,
+        Command::Hooks(args) => hooks::execute(args)?,
+        Command::Db(args) => db::execute(args).await?,
+        Command::Redact(args) => redact::execute(args)?,
+        Command::Config(args) => config::execute(args)?,
     }
     info!("command completed");
     Ok(())
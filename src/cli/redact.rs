@@ -0,0 +1,49 @@
+use super::RedactArgs;
+use crate::{config, daemon::Redactor, errors::Result};
+
+const PREVIEW_CHARS: usize = 60;
+
+pub fn execute(args: RedactArgs) -> Result<()> {
+    let config = config::load()?;
+    let redactor = Redactor::new(&config.redaction)?;
+    let input = std::fs::read_to_string(&args.test)?;
+
+    let findings = redactor.find(&input, None);
+    if findings.is_empty() {
+        println!("Nothing in {} would be redacted.", args.test.display());
+        return Ok(());
+    }
+    for finding in &findings {
+        let before = &input[..finding.start];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+        println!(
+            "{}:{line}:{column} | [REDACTED:{}] | {}",
+            args.test.display(),
+            finding.label,
+            preview(&input[finding.start..finding.end])
+        );
+    }
+    println!(
+        "{} match{} would be redacted.",
+        findings.len(),
+        if findings.len() == 1 { "" } else { "es" }
+    );
+    Ok(())
+}
+
+fn preview(matched: &str) -> String {
+    let flat = matched.replace('\n', "\\n");
+    if flat.chars().count() <= PREVIEW_CHARS {
+        return flat;
+    }
+    let mut out = flat.chars().take(PREVIEW_CHARS - 1).collect::<String>();
+    out.push('…');
+    out
+}
@@ -4,7 +4,7 @@ use tokio::{net::UnixStream, process::Command, time::sleep};
 use tracing::info;
 use ulid::Ulid;
 
-use super::RunArgs;
+use super::{InitArgs, RunArgs};
 use crate::{
     config,
     errors::{AppError, Result},
@@ -14,7 +14,7 @@ pub async fn execute(args: RunArgs) -> Result<()> {
     let mut config = config::load().unwrap_or_default();
     if !config.is_configured() {
         println!("First run detected. Starting guided setup...");
-        super::init::execute().await?;
+        super::init::execute(InitArgs::default()).await?;
         config = config::load()?;
         if !config.is_configured() {
             return Err(AppError::NotConfigured);
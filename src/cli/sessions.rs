@@ -1,25 +1,31 @@
-use crate::{
-    config, db,
-    errors::{AppError, Result},
-};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub async fn execute() -> Result<()> {
+use super::SessionsArgs;
+use crate::{config, daemon, db, errors::Result};
+
+pub async fn execute(args: SessionsArgs) -> Result<()> {
+    // Reads only the local database, so no bot token is needed.
     let config = config::load()?;
-    if !config.is_configured() {
-        return Err(AppError::NotConfigured);
-    }
 
     let pool = db::connect(&config).await?;
-    let sessions = db::list_sessions(&pool).await?;
+    let sessions = db::list_sessions(&pool, args.all).await?;
     if sessions.is_empty() {
         println!("No tracked sessions yet.");
         return Ok(());
     }
 
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
     for session in sessions {
         println!(
-            "{} ({}) | {} | pane={} | last_seen={}",
-            session.name, session.session_id, session.cwd, session.tmux_pane, session.last_seen_at
+            "{} ({}) | {} | {} | pane={}",
+            session.name,
+            session.session_id,
+            daemon::session_summary(&session, now),
+            session.cwd,
+            session.tmux_pane
         );
     }
     Ok(())
@@ -55,11 +55,12 @@ pub async fn execute() -> Result<()> {
         println!("⚠️ tmux not available");
     }
 
-    match crate::daemon::get_bot_username(config.token()?).await {
-        Ok(username) => println!("✅ Telegram auth ok (@{username})"),
+    let transport = config.transport.label();
+    match crate::daemon::transport_identity(&config).await {
+        Ok(identity) => println!("✅ {transport} auth ok ({identity})"),
         Err(_) => {
             ready = false;
-            println!("⚠️ Telegram auth failed");
+            println!("⚠️ {transport} auth failed");
         }
     }
 
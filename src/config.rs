@@ -1,72 +1,543 @@
-use std::{env, fs, path::PathBuf};
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
 
+use clap::ValueEnum;
 use directories::BaseDirs;
 use figment::{
     Figment,
     providers::{Env, Format, Serialized, Toml},
 };
+use regex::Regex;
+use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 
-use crate::errors::{AppError, Result};
+use crate::{
+    errors::{AppError, Result},
+    secrets,
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
+    #[serde(default)]
+    pub transport: TransportKind,
+    #[serde(default)]
+    pub secret_store: SecretStoreKind,
     #[serde(default)]
     pub telegram_bot_token: Option<String>,
     #[serde(default)]
     pub telegram_chat_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub telegram_authorized_chat_ids: Vec<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub telegram_authorized_user_ids: Vec<i64>,
+    #[serde(
+        default = "default_telegram_api_url",
+        skip_serializing_if = "is_default_telegram_api_url"
+    )]
+    pub telegram_api_url: String,
+    #[serde(default)]
+    pub slack_bot_token: Option<String>,
+    #[serde(default)]
+    pub slack_app_token: Option<String>,
+    #[serde(default)]
+    pub slack_channel_id: Option<String>,
+    #[serde(default = "default_slack_api_url")]
+    pub slack_api_url: String,
     #[serde(default = "default_auto_deny_seconds")]
     pub auto_deny_seconds: u64,
     #[serde(default = "default_hook_timeout_seconds")]
     pub hook_timeout_seconds: u64,
+    #[serde(default = "default_countdown_update_seconds")]
+    pub countdown_update_seconds: u64,
+    #[serde(default = "default_reminder_percents")]
+    pub reminder_percents: Vec<u8>,
+    #[serde(default = "default_extend_seconds")]
+    pub extend_seconds: u64,
+    #[serde(default)]
+    pub on_timeout: TimeoutOutcome,
+    #[serde(default = "default_true")]
+    pub batch_permissions: bool,
+    #[serde(default = "default_hook_events")]
+    pub hook_events: Vec<String>,
+    #[serde(default)]
+    pub hook_matchers: BTreeMap<String, String>,
     #[serde(default = "default_context_lines")]
     pub context_lines: usize,
+    #[serde(default = "default_session_check_seconds")]
+    pub session_check_seconds: u64,
+    #[serde(default = "default_session_archive_seconds")]
+    pub session_archive_seconds: u64,
     #[serde(default = "default_max_inline_length")]
     pub max_inline_length: usize,
     #[serde(default = "default_socket_path")]
     pub socket_path: String,
     #[serde(default = "default_db_path")]
     pub db_path: String,
+    #[serde(default)]
+    pub policy_rules: Vec<PolicyRule>,
+    #[serde(default)]
+    pub gate: GateConfig,
+    #[serde(default)]
+    pub routes: Vec<RouteRule>,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    #[default]
+    Telegram,
+    Slack,
+}
+
+impl TransportKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Telegram => "Telegram",
+            Self::Slack => "Slack",
+        }
+    }
+}
+
+// Where bot tokens live. `config` keeps them in config.toml; `env` expects
+// CODELATCH_* variables at runtime and stores nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretStoreKind {
+    #[default]
+    Config,
+    Keyring,
+    File,
+    Env,
+}
+
+impl SecretStoreKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::Keyring => "keyring",
+            Self::File => "file",
+            Self::Env => "env",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    Ask,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PolicyRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub action: PolicyAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd_prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_timeout: Option<TimeoutOutcome>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutOutcome {
+    #[default]
+    Deny,
+    Allow,
+    Pause,
+}
+
+impl TimeoutOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deny => "deny",
+            Self::Allow => "allow",
+            Self::Pause => "pause",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "deny" => Some(Self::Deny),
+            "allow" => Some(Self::Allow),
+            "pause" => Some(Self::Pause),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestClass {
+    Destructive,
+    Network,
+    OutsideCwd,
+    Mcp,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RouteRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub class: RequestClass,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approver_ids: Vec<i64>,
+    #[serde(default = "default_required_approvals")]
+    pub required_approvals: usize,
+}
+
+impl RouteRule {
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GateAction {
+    #[default]
+    Block,
+    Escalate,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GateConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub on_match: GateAction,
+    #[serde(default = "default_gate_deny_commands")]
+    pub deny_commands: Vec<String>,
+    #[serde(default = "default_gate_protected_files")]
+    pub protected_files: Vec<String>,
+    #[serde(default = "default_true")]
+    pub block_writes_outside_repo: bool,
+}
+
+impl Default for GateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            on_match: GateAction::default(),
+            deny_commands: default_gate_deny_commands(),
+            protected_files: default_gate_protected_files(),
+            block_writes_outside_repo: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RedactionConfig {
+    #[serde(default = "default_true")]
+    pub entropy: bool,
+    #[serde(default = "default_entropy_threshold")]
+    pub entropy_threshold: f64,
+    #[serde(default = "default_entropy_min_length")]
+    pub entropy_min_length: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowlist: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RedactionRule>,
+    #[serde(default)]
+    pub session_secrets: bool,
+    #[serde(default = "default_secret_files")]
+    pub secret_files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secret_env_vars: Vec<String>,
+    #[serde(default = "default_secret_min_length")]
+    pub secret_min_length: usize,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            entropy: true,
+            entropy_threshold: default_entropy_threshold(),
+            entropy_min_length: default_entropy_min_length(),
+            allowlist: Vec::new(),
+            rules: Vec::new(),
+            session_secrets: false,
+            secret_files: default_secret_files(),
+            secret_env_vars: Vec::new(),
+            secret_min_length: default_secret_min_length(),
+        }
+    }
+}
+
+// A rule named like a built-in replaces or (with `enabled = false`) disables
+// it; any other name adds a rule and needs a regex.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RedactionRule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            transport: TransportKind::default(),
+            secret_store: SecretStoreKind::default(),
             telegram_bot_token: None,
             telegram_chat_id: None,
+            telegram_authorized_chat_ids: Vec::new(),
+            telegram_authorized_user_ids: Vec::new(),
+            telegram_api_url: default_telegram_api_url(),
+            slack_bot_token: None,
+            slack_app_token: None,
+            slack_channel_id: None,
+            slack_api_url: default_slack_api_url(),
             auto_deny_seconds: default_auto_deny_seconds(),
             hook_timeout_seconds: default_hook_timeout_seconds(),
+            countdown_update_seconds: default_countdown_update_seconds(),
+            reminder_percents: default_reminder_percents(),
+            extend_seconds: default_extend_seconds(),
+            on_timeout: TimeoutOutcome::default(),
+            batch_permissions: true,
+            hook_events: default_hook_events(),
+            hook_matchers: BTreeMap::new(),
             context_lines: default_context_lines(),
+            session_check_seconds: default_session_check_seconds(),
+            session_archive_seconds: default_session_archive_seconds(),
             max_inline_length: default_max_inline_length(),
             socket_path: default_socket_path(),
             db_path: default_db_path(),
+            policy_rules: Vec::new(),
+            gate: GateConfig::default(),
+            routes: Vec::new(),
+            redaction: RedactionConfig::default(),
         }
     }
 }
 
 impl Config {
     pub fn is_configured(&self) -> bool {
-        self.telegram_bot_token
-            .as_deref()
-            .map(str::trim)
-            .is_some_and(|token| !token.is_empty())
-            && self.telegram_chat_id.is_some_and(|chat_id| chat_id != 0)
+        match self.transport {
+            TransportKind::Telegram => {
+                self.has_secret(self.telegram_bot_token.as_deref())
+                    && self.telegram_chat_id.is_some_and(|chat_id| chat_id != 0)
+            }
+            TransportKind::Slack => {
+                self.has_secret(self.slack_bot_token.as_deref())
+                    && self.has_secret(self.slack_app_token.as_deref())
+                    && self.slack_channel_id().is_ok()
+            }
+        }
+    }
+
+    // Cheap enough for every CLI command: a keyring or file store is trusted
+    // to hold the secret until something actually needs it.
+    fn has_secret(&self, plaintext: Option<&str>) -> bool {
+        non_empty(plaintext).is_ok()
+            || matches!(
+                self.secret_store,
+                SecretStoreKind::Keyring | SecretStoreKind::File
+            )
     }
 
-    pub fn token(&self) -> Result<&str> {
-        self.telegram_bot_token
-            .as_deref()
-            .filter(|token| !token.trim().is_empty())
+    // A plaintext value (from config.toml or a CODELATCH_* variable) wins, so
+    // env injection works with any store.
+    fn secret(&self, name: &str, plaintext: Option<&str>) -> Result<SecretString> {
+        if let Ok(value) = non_empty(plaintext) {
+            return Ok(value.to_string().into());
+        }
+        secrets::get(self.secret_store, name)?
+            .filter(|value| !value.trim().is_empty())
+            .map(SecretString::from)
             .ok_or(AppError::NotConfigured)
     }
 
+    pub fn token(&self) -> Result<SecretString> {
+        self.secret(
+            secrets::TELEGRAM_BOT_TOKEN,
+            self.telegram_bot_token.as_deref(),
+        )
+    }
+
     pub fn chat_id(&self) -> Result<i64> {
         self.telegram_chat_id
             .filter(|chat_id| *chat_id != 0)
             .ok_or(AppError::NotConfigured)
     }
+
+    pub fn installed_hook_events(&self) -> Vec<&str> {
+        let mut events = self
+            .hook_events
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if self.gate.enabled && !events.contains(&"PreToolUse") {
+            events.push("PreToolUse");
+        }
+        events
+    }
+
+    pub fn is_blocking_event(&self, event: &str) -> bool {
+        event == "PermissionRequest" || (event == "PreToolUse" && self.gate.enabled)
+    }
+
+    pub fn hook_matcher(&self, event: &str) -> Option<&str> {
+        if let Some(matcher) = self.hook_matchers.get(event) {
+            return Some(matcher);
+        }
+        match event {
+            // The gate only judges commands and file writes; hooking every
+            // other tool would make each call wait on the daemon for nothing.
+            "PreToolUse" if self.gate.enabled => Some(GATE_TOOL_MATCHER),
+            "Notification" => Some("elicitation_dialog"),
+            "PermissionRequest" | "PostToolUseFailure" | "PreToolUse" | "PostToolUse" => Some(""),
+            _ => None,
+        }
+    }
+
+    // Mirrors Claude Code: an empty matcher or `*` takes everything, anything
+    // else is a regex that has to match the whole name.
+    pub fn hook_matches(&self, event: &str, name: &str) -> bool {
+        match self.hook_matcher(event) {
+            None | Some("") | Some("*") => true,
+            Some(matcher) => Regex::new(&format!("^(?:{matcher})$"))
+                .map(|regex| regex.is_match(name))
+                .unwrap_or(matcher == name),
+        }
+    }
+
+    // Claude Code kills a hook that outlives its timeout and falls back to the
+    // local prompt, so the daemon's auto-deny has to fire first.
+    pub fn validate(&self) -> Result<()> {
+        if self.hook_timeout_seconds <= self.auto_deny_seconds {
+            return Err(AppError::ConfigInvalid(format!(
+                "hook_timeout_seconds ({}) must be greater than auto_deny_seconds ({})",
+                self.hook_timeout_seconds, self.auto_deny_seconds
+            )));
+        }
+        if let Some(percent) = self
+            .reminder_percents
+            .iter()
+            .find(|percent| !(1..=99).contains(*percent))
+        {
+            return Err(AppError::ConfigInvalid(format!(
+                "reminder_percents entry {percent} must be between 1 and 99"
+            )));
+        }
+        // Auto-allowing on timeout is only safe for classes someone listed on
+        // purpose, so it is accepted on `ask` rules and nowhere else.
+        if self.on_timeout == TimeoutOutcome::Allow {
+            return Err(AppError::ConfigInvalid(
+                "on_timeout = \"allow\" is only accepted on policy rules".to_string(),
+            ));
+        }
+        for (index, rule) in self.policy_rules.iter().enumerate() {
+            if rule.on_timeout.is_some() && rule.action != PolicyAction::Ask {
+                let label = rule
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("#{}", index + 1));
+                return Err(AppError::ConfigInvalid(format!(
+                    "policy rule {label} sets on_timeout but its action is not `ask`"
+                )));
+            }
+        }
+        for (index, route) in self.routes.iter().enumerate() {
+            let label = route.label(index);
+            if route.required_approvals == 0 {
+                return Err(AppError::ConfigInvalid(format!(
+                    "route {label} needs required_approvals of at least 1"
+                )));
+            }
+            if !route.approver_ids.is_empty() && route.required_approvals > route.approver_ids.len()
+            {
+                return Err(AppError::ConfigInvalid(format!(
+                    "route {label} requires {} approvals but lists {} approver_ids",
+                    route.required_approvals,
+                    route.approver_ids.len()
+                )));
+            }
+            if route.mcp_server.is_some() && route.class != RequestClass::Mcp {
+                return Err(AppError::ConfigInvalid(format!(
+                    "route {label} sets mcp_server but its class is not `mcp`"
+                )));
+            }
+            // Chat and user ids are Telegram's; Slack posts to its one channel.
+            if self.transport == TransportKind::Slack
+                && (route.chat_id.is_some() || !route.approver_ids.is_empty())
+            {
+                return Err(AppError::ConfigInvalid(format!(
+                    "route {label} sets chat_id or approver_ids, which need the Telegram transport"
+                )));
+            }
+        }
+        let known = |event: &str| {
+            DEFAULT_HOOK_EVENTS.contains(&event) || OPTIONAL_HOOK_EVENTS.contains(&event)
+        };
+        for event in self.hook_events.iter().chain(self.hook_matchers.keys()) {
+            if !known(event) {
+                return Err(AppError::ConfigInvalid(format!(
+                    "unknown hook event `{event}`"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn slack_bot_token(&self) -> Result<SecretString> {
+        self.secret(secrets::SLACK_BOT_TOKEN, self.slack_bot_token.as_deref())
+    }
+
+    pub fn slack_app_token(&self) -> Result<SecretString> {
+        self.secret(secrets::SLACK_APP_TOKEN, self.slack_app_token.as_deref())
+    }
+
+    pub fn slack_channel_id(&self) -> Result<&str> {
+        non_empty(self.slack_channel_id.as_deref())
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Result<&str> {
+    value
+        .filter(|value| !value.trim().is_empty())
+        .ok_or(AppError::NotConfigured)
 }
 
+pub const GATE_TOOL_MATCHER: &str = "Bash|Write|Edit|MultiEdit|NotebookEdit";
+
+pub const DEFAULT_HOOK_EVENTS: &[&str] = &[
+    "PermissionRequest",
+    "Notification",
+    "PostToolUseFailure",
+    "Stop",
+    "SessionStart",
+    "SessionEnd",
+];
+
+pub const OPTIONAL_HOOK_EVENTS: &[&str] = &[
+    "PreToolUse",
+    "PostToolUse",
+    "UserPromptSubmit",
+    "SubagentStop",
+    "PreCompact",
+];
+
 pub fn load() -> Result<Config> {
     let path = config_path()?;
     let mut figment =
@@ -76,7 +547,9 @@ pub fn load() -> Result<Config> {
         figment = figment.merge(Toml::file(&path));
     }
 
-    figment.extract().map_err(|_| AppError::ConfigLoad)
+    let config: Config = figment.extract().map_err(|_| AppError::ConfigLoad)?;
+    config.validate()?;
+    Ok(config)
 }
 
 pub fn save(config: &Config) -> Result<()> {
@@ -88,7 +561,15 @@ pub fn save(config: &Config) -> Result<()> {
     fs::create_dir_all(parent)
         .map_err(|_| AppError::CreateConfigDir(parent.display().to_string()))?;
 
-    let toml_text = toml::to_string_pretty(config).map_err(|_| AppError::ConfigSerialize)?;
+    // Tokens loaded from a store or the environment must never be written
+    // back as plaintext.
+    let mut config = config.clone();
+    if config.secret_store != SecretStoreKind::Config {
+        config.telegram_bot_token = None;
+        config.slack_bot_token = None;
+        config.slack_app_token = None;
+    }
+    let toml_text = toml::to_string_pretty(&config).map_err(|_| AppError::ConfigSerialize)?;
     fs::write(&path, toml_text).map_err(|_| AppError::WriteConfig(path.display().to_string()))?;
 
     #[cfg(unix)]
@@ -129,6 +610,10 @@ pub fn lock_path() -> Result<PathBuf> {
     Ok(data_dir()?.join("codelatchd.lock"))
 }
 
+fn default_required_approvals() -> usize {
+    1
+}
+
 fn default_auto_deny_seconds() -> u64 {
     600
 }
@@ -137,14 +622,92 @@ fn default_hook_timeout_seconds() -> u64 {
     3600
 }
 
+fn default_countdown_update_seconds() -> u64 {
+    60
+}
+
+fn default_reminder_percents() -> Vec<u8> {
+    vec![50, 90]
+}
+
+fn default_extend_seconds() -> u64 {
+    600
+}
+
+fn default_hook_events() -> Vec<String> {
+    DEFAULT_HOOK_EVENTS
+        .iter()
+        .map(|event| event.to_string())
+        .collect()
+}
+
+fn default_gate_deny_commands() -> Vec<String> {
+    vec![
+        r"(?i)\brm\s+(-\S+\s+)*(-[a-z]*r|--recursive)".to_string(),
+        r"\bgit\s+push\b.*\s(--force|--force-with-lease|-f)(\s|=|$)".to_string(),
+        r"\bgit\s+(reset\s+--hard|clean\s+-[a-zA-Z]*f)".to_string(),
+        r"\b(mkfs(\.\w+)?|shred|wipefs)\b".to_string(),
+        r"\bdd\s+.*\bof=".to_string(),
+        r"\b(chmod|chown)\s+(-\S+\s+)*-[a-zA-Z]*R".to_string(),
+        r"(?i)\b(drop|truncate)\s+(table|database|schema)\b".to_string(),
+        r"\b(terraform\s+destroy|kubectl\s+delete|helm\s+uninstall)\b".to_string(),
+    ]
+}
+
+fn default_gate_protected_files() -> Vec<String> {
+    vec![".env".to_string(), ".env.*".to_string()]
+}
+
+fn default_entropy_threshold() -> f64 {
+    4.0
+}
+
+fn default_entropy_min_length() -> usize {
+    24
+}
+
+fn default_secret_files() -> Vec<String> {
+    vec![".env".to_string(), ".env.local".to_string()]
+}
+
+fn default_secret_min_length() -> usize {
+    8
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_context_lines() -> usize {
     15
 }
 
+fn default_session_check_seconds() -> u64 {
+    30
+}
+
+fn default_session_archive_seconds() -> u64 {
+    3600
+}
+
 fn default_max_inline_length() -> usize {
     4096
 }
 
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+// Left out of saved configs so `CODELATCH_TELEGRAM_API_URL` is not shadowed by
+// the file after `codelatch init`.
+fn is_default_telegram_api_url(url: &str) -> bool {
+    url == default_telegram_api_url()
+}
+
+fn default_slack_api_url() -> String {
+    "https://slack.com/api".to_string()
+}
+
 fn default_socket_path() -> String {
     if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
         return PathBuf::from(runtime_dir)
@@ -165,7 +728,7 @@ fn default_db_path() -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::Config;
+    use super::{Config, PolicyAction, PolicyRule, SecretStoreKind, TimeoutOutcome, TransportKind};
 
     #[test]
     fn defaults_are_safe() {
@@ -173,5 +736,105 @@ mod tests {
         assert!(!config.is_configured());
         assert_eq!(config.auto_deny_seconds, 600);
         assert_eq!(config.hook_timeout_seconds, 3600);
+        assert!(config.policy_rules.is_empty());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn tokens_can_live_outside_config_toml() {
+        let mut config = Config {
+            telegram_bot_token: Some("123:abc".to_string()),
+            telegram_chat_id: Some(42),
+            ..Config::default()
+        };
+        assert!(config.is_configured());
+
+        // Nothing holds the token for `env` until the variable is exported.
+        assert!(crate::secrets::migrate(&mut config, SecretStoreKind::Env).is_err());
+        assert_eq!(config.telegram_bot_token.as_deref(), Some("123:abc"));
+
+        config.telegram_bot_token = None;
+        config.secret_store = SecretStoreKind::Env;
+        assert!(!config.is_configured());
+
+        config.secret_store = SecretStoreKind::Keyring;
+        assert!(config.is_configured());
+        assert!(crate::secrets::migrate(&mut config, SecretStoreKind::Config).is_err());
+    }
+
+    #[test]
+    fn hook_settings_are_validated() {
+        let config = Config {
+            auto_deny_seconds: 900,
+            hook_timeout_seconds: 900,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.hook_events.push("PreCompact".to_string());
+        assert!(config.validate().is_ok());
+        config
+            .hook_matchers
+            .insert("PreToolCall".to_string(), "Bash".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn timeout_allow_is_only_accepted_on_ask_rules() {
+        let config = Config {
+            on_timeout: TimeoutOutcome::Allow,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+
+        let mut rule = PolicyRule {
+            name: Some("reads".to_string()),
+            action: PolicyAction::Ask,
+            tool: Some("Read".to_string()),
+            command: None,
+            glob: None,
+            regex: None,
+            cwd_prefix: None,
+            on_timeout: Some(TimeoutOutcome::Allow),
+        };
+        let mut config = Config {
+            on_timeout: TimeoutOutcome::Pause,
+            policy_rules: vec![rule.clone()],
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+        rule.action = PolicyAction::Deny;
+        config.policy_rules = vec![rule];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn custom_telegram_api_url_survives_save() {
+        let saved = toml::to_string_pretty(&Config::default()).expect("serialize");
+        assert!(!saved.contains("telegram_api_url"));
+
+        let config = Config {
+            telegram_api_url: "http://127.0.0.1:8081".to_string(),
+            ..Config::default()
+        };
+        let saved = toml::to_string_pretty(&config).expect("serialize");
+        let loaded: Config = toml::from_str(&saved).expect("deserialize");
+        assert_eq!(loaded.telegram_api_url, "http://127.0.0.1:8081");
+    }
+
+    #[test]
+    fn slack_transport_needs_both_tokens_and_a_channel() {
+        let mut config = Config {
+            transport: TransportKind::Slack,
+            telegram_bot_token: Some("123:abc".to_string()),
+            telegram_chat_id: Some(42),
+            slack_bot_token: Some("xoxb-1".to_string()),
+            slack_channel_id: Some("C123".to_string()),
+            ..Config::default()
+        };
+        assert!(!config.is_configured());
+        config.slack_app_token = Some("xapp-1".to_string());
+        assert!(config.is_configured());
     }
 }
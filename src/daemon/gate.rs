@@ -0,0 +1,182 @@
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use super::policy::{PermissionSubject, glob_regex};
+use crate::{
+    config::GateConfig,
+    errors::{AppError, Result},
+};
+
+pub const WRITE_TOOLS: &[&str] = &["Write", "Edit", "MultiEdit", "NotebookEdit"];
+
+// Bash commands treated as destructive. The gate's deny-list and the
+// `destructive` route class both read `gate.deny_commands`, so a command the
+// gate would stop is always one a destructive route catches.
+pub struct DenyList {
+    commands: Vec<(String, Regex)>,
+}
+
+impl DenyList {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        Ok(Self {
+            commands: patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern)
+                        .map(|regex| (pattern.clone(), regex))
+                        .map_err(|err| AppError::GateRule(format!("{pattern}: {err}")))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn matching(&self, command: &str) -> Option<&str> {
+        self.commands
+            .iter()
+            .find(|(_, regex)| regex.is_match(command))
+            .map(|(pattern, _)| pattern.as_str())
+    }
+}
+
+pub struct Gate {
+    commands: DenyList,
+    protected_files: Vec<(String, Regex)>,
+    block_writes_outside_repo: bool,
+}
+
+impl Gate {
+    pub fn new(config: &GateConfig) -> Result<Self> {
+        let compile = |pattern: &String, regex: std::result::Result<Regex, regex::Error>| {
+            regex
+                .map(|regex| (pattern.clone(), regex))
+                .map_err(|err| AppError::GateRule(format!("{pattern}: {err}")))
+        };
+        Ok(Self {
+            commands: DenyList::new(&config.deny_commands)?,
+            protected_files: config
+                .protected_files
+                .iter()
+                .map(|pattern| compile(pattern, glob_regex(pattern)))
+                .collect::<Result<_>>()?,
+            block_writes_outside_repo: config.block_writes_outside_repo,
+        })
+    }
+
+    pub fn evaluate(&self, subject: &PermissionSubject<'_>) -> Option<String> {
+        let value = subject.subject?.trim();
+        if subject.tool_name == "Bash" {
+            return self
+                .commands
+                .matching(value)
+                .map(|pattern| format!("command matches deny-list pattern `{pattern}`"));
+        }
+        if !WRITE_TOOLS.contains(&subject.tool_name) {
+            return None;
+        }
+
+        let path = normalize(&Path::new(subject.cwd).join(value));
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Some((pattern, _)) = self
+            .protected_files
+            .iter()
+            .find(|(_, regex)| regex.is_match(&file_name))
+        {
+            return Some(format!("{file_name} is a protected file (`{pattern}`)"));
+        }
+        let root = repo_root(Path::new(subject.cwd));
+        if self.block_writes_outside_repo && !path.starts_with(&root) {
+            return Some(format!(
+                "{} is outside the repository {}",
+                path.display(),
+                root.display()
+            ));
+        }
+        None
+    }
+}
+
+fn repo_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gate;
+    use crate::{config::GateConfig, daemon::policy::PermissionSubject};
+
+    fn subject<'a>(tool_name: &'a str, value: &'a str) -> PermissionSubject<'a> {
+        PermissionSubject {
+            tool_name,
+            subject: Some(value),
+            cwd: "/work/repo",
+        }
+    }
+
+    #[test]
+    fn default_deny_list_catches_destructive_calls() {
+        let gate = Gate::new(&GateConfig::default()).expect("gate compiles");
+        for command in [
+            "rm -rf target",
+            "rm -v -Rf /",
+            "git push origin main --force",
+            "git push -f",
+            "git reset --hard HEAD~3",
+            "psql -c 'DROP TABLE users'",
+        ] {
+            assert!(
+                gate.evaluate(&subject("Bash", command)).is_some(),
+                "{command}"
+            );
+        }
+        for command in [
+            "rm target/debug/foo",
+            "git push origin main",
+            "cargo test -f",
+        ] {
+            assert!(
+                gate.evaluate(&subject("Bash", command)).is_none(),
+                "{command}"
+            );
+        }
+
+        let env = gate.evaluate(&subject("Edit", "config/.env.local"));
+        assert!(env.is_some_and(|reason| reason.contains(".env.local")));
+        assert!(
+            gate.evaluate(&subject("Write", "../other/lib.rs"))
+                .is_some()
+        );
+        assert!(gate.evaluate(&subject("Write", "/etc/hosts")).is_some());
+        assert!(gate.evaluate(&subject("Write", "src/lib.rs")).is_none());
+        assert!(gate.evaluate(&subject("Read", "/etc/hosts")).is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let config = GateConfig {
+            deny_commands: vec!["(unclosed".to_string()],
+            ..GateConfig::default()
+        };
+        assert!(Gate::new(&config).is_err());
+    }
+}
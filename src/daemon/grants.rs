@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use super::policy::PermissionSubject;
+use crate::db::GrantRecord;
+
+const FILE_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit", "Read"];
+pub const SHELL_OPERATORS: &[char] = &[';', '&', '|', '<', '>', '`', '$', '\n', '(', ')'];
+// Tools whose second word picks what they do. Only these are granted by
+// prefix; for anything else the arguments are the risk (`rm -rf target` vs
+// `rm -rf ~`, `python x.py`, `bash -c ...`), so the grant is exact.
+const SUBCOMMAND_TOOLS: &[&str] = &[
+    "git", "cargo", "npm", "pnpm", "yarn", "bun", "go", "make", "just", "docker", "gh", "uv",
+    "poetry", "rustup", "dotnet", "mvn", "gradle",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrantKind {
+    Exact,
+    Prefix,
+    Path,
+    Domain,
+    Tool,
+}
+
+impl GrantKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Prefix => "prefix",
+            Self::Path => "path",
+            Self::Domain => "domain",
+            Self::Tool => "tool",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "exact" => Some(Self::Exact),
+            "prefix" => Some(Self::Prefix),
+            "path" => Some(Self::Path),
+            "domain" => Some(Self::Domain),
+            "tool" => Some(Self::Tool),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantPattern {
+    pub tool_name: String,
+    pub kind: GrantKind,
+    pub pattern: String,
+}
+
+impl GrantPattern {
+    pub fn derive(subject: &PermissionSubject<'_>) -> Self {
+        let tool_name = subject.tool_name.to_string();
+        let value = subject.subject.map(str::trim).unwrap_or_default();
+
+        if subject.tool_name == "Bash" {
+            let mut tokens = value.split_whitespace();
+            if !value.contains(SHELL_OPERATORS)
+                && let (Some(program), Some(second)) = (tokens.next(), tokens.next())
+                && SUBCOMMAND_TOOLS.contains(&program)
+                && looks_like_subcommand(second)
+            {
+                return Self {
+                    tool_name,
+                    kind: GrantKind::Prefix,
+                    pattern: format!("{program} {second}"),
+                };
+            }
+            return Self {
+                tool_name,
+                kind: GrantKind::Exact,
+                pattern: value.to_string(),
+            };
+        }
+
+        if FILE_TOOLS.contains(&subject.tool_name) && !value.is_empty() {
+            let path = Path::new(value);
+            let parent = path
+                .parent()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default();
+            let file_pattern = match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => format!("*.{ext}"),
+                None => "*".to_string(),
+            };
+            return Self {
+                tool_name,
+                kind: GrantKind::Path,
+                pattern: format!("{parent}/{file_pattern}"),
+            };
+        }
+
+        if subject.tool_name == "WebFetch"
+            && let Some(host) = url_host(value)
+        {
+            return Self {
+                tool_name,
+                kind: GrantKind::Domain,
+                pattern: host.to_string(),
+            };
+        }
+
+        Self {
+            tool_name,
+            kind: GrantKind::Tool,
+            pattern: String::new(),
+        }
+    }
+
+    pub fn from_record(record: &GrantRecord) -> Option<Self> {
+        Some(Self {
+            tool_name: record.tool_name.clone(),
+            kind: GrantKind::parse(&record.match_kind)?,
+            pattern: record.pattern.clone(),
+        })
+    }
+
+    pub fn matches(&self, subject: &PermissionSubject<'_>) -> bool {
+        if self.tool_name != subject.tool_name {
+            return false;
+        }
+        let value = subject.subject.map(str::trim).unwrap_or_default();
+        match self.kind {
+            GrantKind::Exact => value == self.pattern,
+            GrantKind::Prefix => {
+                !value.contains(SHELL_OPERATORS)
+                    && (value == self.pattern
+                        || value
+                            .strip_prefix(&self.pattern)
+                            .is_some_and(|rest| rest.starts_with(' ')))
+            }
+            GrantKind::Path => {
+                let Some((dir, file_pattern)) = self.pattern.rsplit_once('/') else {
+                    return false;
+                };
+                let Some((value_dir, file_name)) = value.rsplit_once('/') else {
+                    return false;
+                };
+                dir == value_dir
+                    && match file_pattern.strip_prefix('*') {
+                        Some(suffix) => file_name.ends_with(suffix),
+                        None => file_name == file_pattern,
+                    }
+            }
+            GrantKind::Domain => url_host(value) == Some(self.pattern.as_str()),
+            GrantKind::Tool => true,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.kind {
+            GrantKind::Exact => format!("{} {}", self.tool_name, self.pattern),
+            GrantKind::Prefix => format!("{} {} …", self.tool_name, self.pattern),
+            GrantKind::Path => format!("{} {}", self.tool_name, self.pattern),
+            GrantKind::Domain => format!("{} {}", self.tool_name, self.pattern),
+            GrantKind::Tool => format!("{} (any input)", self.tool_name),
+        }
+    }
+}
+
+fn looks_like_subcommand(token: &str) -> bool {
+    !token.starts_with('-')
+        && token
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == ':')
+}
+
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GrantKind, GrantPattern};
+    use crate::daemon::policy::PermissionSubject;
+
+    fn subject<'a>(tool_name: &'a str, value: &'a str) -> PermissionSubject<'a> {
+        PermissionSubject {
+            tool_name,
+            subject: Some(value),
+            cwd: "/work/repo",
+        }
+    }
+
+    #[test]
+    fn bash_grants_cover_the_command_prefix_only() {
+        let grant = GrantPattern::derive(&subject("Bash", "cargo test --workspace"));
+        assert_eq!(grant.kind, GrantKind::Prefix);
+        assert_eq!(grant.pattern, "cargo test");
+
+        assert!(grant.matches(&subject("Bash", "cargo test")));
+        assert!(grant.matches(&subject("Bash", "cargo test -p codelatch")));
+        assert!(!grant.matches(&subject("Bash", "cargo testing")));
+        assert!(!grant.matches(&subject("Bash", "cargo test && rm -rf ~")));
+    }
+
+    #[test]
+    fn other_programs_only_grant_the_exact_command() {
+        for command in [
+            "rm -rf target",
+            "python x.py",
+            "bash -c 'make'",
+            "git --version",
+        ] {
+            let grant = GrantPattern::derive(&subject("Bash", command));
+            assert_eq!(grant.kind, GrantKind::Exact, "{command}");
+            assert_eq!(grant.pattern, command);
+        }
+        let grant = GrantPattern::derive(&subject("Bash", "rm -rf target"));
+        assert!(!grant.matches(&subject("Bash", "rm -rf ~")));
+    }
+
+    #[test]
+    fn compound_commands_only_grant_the_exact_command() {
+        let grant = GrantPattern::derive(&subject("Bash", "make && make install"));
+        assert_eq!(grant.kind, GrantKind::Exact);
+        assert!(grant.matches(&subject("Bash", "make && make install")));
+        assert!(!grant.matches(&subject("Bash", "make && make clean")));
+    }
+
+    #[test]
+    fn file_grants_stay_in_the_same_directory() {
+        let grant = GrantPattern::derive(&subject("Edit", "/work/repo/src/main.rs"));
+        assert_eq!(grant.kind, GrantKind::Path);
+        assert_eq!(grant.pattern, "/work/repo/src/*.rs");
+
+        assert!(grant.matches(&subject("Edit", "/work/repo/src/lib.rs")));
+        assert!(!grant.matches(&subject("Edit", "/work/repo/src/nested/lib.rs")));
+        assert!(!grant.matches(&subject("Edit", "/work/repo/src/notes.md")));
+        assert!(!grant.matches(&subject("Write", "/work/repo/src/lib.rs")));
+    }
+}
@@ -1,30 +1,34 @@
+mod batch;
+mod gate;
+mod grants;
+mod policy;
+mod redact;
+mod render;
+mod routing;
+mod slack;
+mod telegram;
+mod transport;
+
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     fs::OpenOptions,
-    future::Future,
     io::ErrorKind,
-    num::NonZeroU32,
     path::Path,
+    process::Stdio,
     sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use backoff::{ExponentialBackoff, backoff::Backoff};
 use bytes::Bytes;
 use fs4::fs_std::FileExt;
 use futures_util::{SinkExt, StreamExt};
-use governor::{Quota, RateLimiter};
-use regex::Regex;
-use reqwest::{Client, multipart};
-use secrecy::{ExposeSecret, SecretString};
-use serde::Deserialize;
+use secrecy::ExposeSecret;
 use serde_json::{Value, json};
 use sqlx::SqlitePool;
 use tokio::{
     net::{UnixListener, UnixStream},
     process::Command,
     sync::{Mutex, oneshot},
-    time::sleep,
 };
 use tokio_util::{
     codec::{Framed, LengthDelimitedCodec},
@@ -33,39 +37,116 @@ use tokio_util::{
 use tracing::{error, info, warn};
 
 use crate::{
-    config::{self, Config},
-    db,
+    config::{self, Config, GateAction, PolicyAction, TimeoutOutcome, TransportKind},
+    db::{self, SessionRecord, SessionStatus},
     errors::{AppError, Result},
     models::envelope::{HookEnvelope, HookResponseEnvelope},
 };
+use batch::{BatchItem, LiveItems, PermissionBatch};
+use gate::{DenyList, Gate};
+use grants::GrantPattern;
+use policy::{PermissionSubject, PolicyEngine};
+pub use redact::Redactor;
+use render::PermissionPreview;
+use routing::{Route, Router};
+use slack::SlackClient;
+use telegram::TelegramClient;
+pub use telegram::{get_bot_username, wait_for_start_chat};
+use transport::{
+    Access, Actor, Button, Inbound, InboundCallback, InboundCommand, InboundMessage, Transport,
+};
 
-const TELEGRAM_API: &str = "https://api.telegram.org";
 const PEEK_CONTEXT_LINES: usize = 30;
 const LOG_LINES: usize = 200;
-const MAX_TELEGRAM_TEXT: usize = 4096;
+const PERMISSION_PREVIEW_CHARS: usize = 2500;
+const HOOK_TIMEOUT_MARGIN_SECONDS: i64 = 30;
 
 #[derive(Clone)]
 struct DaemonState {
     config: Config,
     db: SqlitePool,
     redactor: Arc<Redactor>,
-    telegram: TelegramClient,
+    policy: Arc<PolicyEngine>,
+    gate: Arc<Gate>,
+    router: Arc<Router>,
+    transport: Arc<dyn Transport>,
     shutdown: CancellationToken,
-    pending_waiters: Arc<Mutex<HashMap<String, oneshot::Sender<HookResponseEnvelope>>>>,
+    pending_waiters: Arc<Mutex<HashMap<String, PendingWaiter>>>,
+    batches: Arc<Mutex<HashMap<String, PermissionBatch>>>,
+    prompt_lock: Arc<Mutex<()>>,
+}
+
+struct PendingWaiter {
+    sender: oneshot::Sender<HookResponseEnvelope>,
+    audit: AuditContext,
+    grant: GrantPattern,
+    cwd: String,
+    subject: Option<String>,
+    tmux_pane: Option<String>,
+    rule_timeout: Option<TimeoutOutcome>,
+    message_id: i64,
+    transport: Arc<dyn Transport>,
+    route: Option<Route>,
+    approvals: Vec<Actor>,
+    batchable: bool,
+    batch: Option<String>,
+    prompt: PermissionPrompt,
+    window_start: i64,
+    expires_at: i64,
+    reminders_sent: usize,
+    awaiting_note: bool,
+    edit_lock: Arc<Mutex<()>>,
 }
 
 #[derive(Clone)]
-struct TelegramClient {
-    http: Client,
-    token: SecretString,
-    chat_id: i64,
-    limiter: Arc<governor::DefaultDirectRateLimiter>,
+struct AuditContext {
+    request_id: String,
+    session_id: String,
+    session_name: String,
+    tool_name: String,
+    tool_input: String,
+    requested_at: i64,
+    started: Instant,
+}
+
+enum Decider {
+    Operator(Option<Actor>),
+    Timeout,
+    Policy(String),
+    Gate,
+    Grant(i64),
+    DaemonClosed,
+}
+
+impl Decider {
+    fn label(&self) -> String {
+        match self {
+            Self::Operator(_) => "operator".to_string(),
+            Self::Timeout => "timeout".to_string(),
+            Self::Policy(rule) => format!("policy:{rule}"),
+            Self::Gate => "gate".to_string(),
+            Self::Grant(id) => format!("grant:{id}"),
+            Self::DaemonClosed => "daemon_closed".to_string(),
+        }
+    }
+
+    fn actor(&self) -> Option<String> {
+        match self {
+            Self::Operator(actor) => actor.as_ref().map(|actor| actor.name.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn signed(status: &str, actor: Option<&Actor>) -> String {
+    match actor {
+        Some(actor) => format!("{status} by {}", actor.name),
+        None => status.to_string(),
+    }
 }
 
 pub async fn run(config: Config) -> Result<()> {
     let _lock_guard = acquire_singleton_lock()?;
-    let token: SecretString = config.token()?.to_string().into();
-    let chat_id = config.chat_id()?;
     let pid_path = config::pid_path()?;
     if let Some(parent) = pid_path.parent() {
         tokio::fs::create_dir_all(parent).await?;
@@ -79,32 +160,40 @@ pub async fn run(config: Config) -> Result<()> {
         let _ = tokio::fs::remove_file(&config.socket_path).await;
     }
 
+    let policy = PolicyEngine::new(&config.policy_rules)?;
+    let gate = Gate::new(&config.gate)?;
+    let router = Router::new(&config.routes, DenyList::new(&config.gate.deny_commands)?)?;
+    let redactor = Redactor::new(&config.redaction)?;
+    let transport = build_transport(&config)?;
     let db = db::connect(&config).await?;
     let listener = UnixListener::bind(&config.socket_path)?;
     let shutdown_token = CancellationToken::new();
     let state = DaemonState {
         config,
         db,
-        redactor: Arc::new(Redactor::new()?),
-        telegram: TelegramClient {
-            http: Client::new(),
-            token,
-            chat_id,
-            limiter: Arc::new(RateLimiter::direct(Quota::per_second(
-                NonZeroU32::new(20).expect("nonzero"),
-            ))),
-        },
+        redactor: Arc::new(redactor),
+        policy: Arc::new(policy),
+        gate: Arc::new(gate),
+        router: Arc::new(router),
+        transport,
         shutdown: shutdown_token.clone(),
         pending_waiters: Arc::new(Mutex::new(HashMap::new())),
+        batches: Arc::new(Mutex::new(HashMap::new())),
+        prompt_lock: Arc::new(Mutex::new(())),
     };
 
+    reconcile_pending_requests(&state).await;
+    adopt_legacy_reply_routes(&state).await;
+    reload_session_secrets(&state).await;
+
     info!(socket = %state.config.socket_path, "daemon listening");
-    let long_poll_state = state.clone();
-    let long_poll = tokio::spawn(async move {
-        if let Err(err) = long_poll_loop(long_poll_state).await {
-            warn!(error = %err, "telegram long poll loop stopped");
+    let receive_state = state.clone();
+    let receiver = tokio::spawn(async move {
+        if let Err(err) = receive_loop(receive_state).await {
+            warn!(error = %err, "transport receive loop stopped");
         }
     });
+    let watcher = tokio::spawn(watch_sessions(state.clone()));
     let mut shutdown_signal = Box::pin(tokio::signal::ctrl_c());
 
     loop {
@@ -130,12 +219,156 @@ pub async fn run(config: Config) -> Result<()> {
     }
 
     state.shutdown.cancel();
-    let _ = long_poll.await;
+    let _ = receiver.await;
+    let _ = watcher.await;
     let _ = tokio::fs::remove_file(&state.config.socket_path).await;
     let _ = tokio::fs::remove_file(pid_path).await;
     Ok(())
 }
 
+async fn reconcile_pending_requests(state: &DaemonState) {
+    let abandoned = match db::abandon_waiting_requests(&state.db, now_epoch()).await {
+        Ok(abandoned) => abandoned,
+        Err(err) => {
+            warn!(error = %err, "failed to reconcile pending permission requests");
+            return;
+        }
+    };
+    if abandoned.is_empty() {
+        return;
+    }
+
+    info!(
+        count = abandoned.len(),
+        "marked orphaned permission requests as abandoned"
+    );
+    // Batched requests share one digest message, so each is edited once.
+    // Message ids are per chat, so the key includes the approver chat.
+    let mut edited = HashSet::new();
+    for request in abandoned {
+        let Some(message_id) = request.telegram_message_id else {
+            continue;
+        };
+        if !edited.insert((request.approver_chat_id, message_id)) {
+            continue;
+        }
+        let transport = match request.approver_chat_id {
+            Some(chat_id) => state
+                .transport
+                .to_chat(chat_id)
+                .unwrap_or_else(|| state.transport.clone()),
+            None => state.transport.clone(),
+        };
+        if let Err(err) = transport
+            .edit_text(
+                message_id,
+                "🔴 Permission\n\n⚪ Abandoned — the daemon restarted before a decision was made.",
+            )
+            .await
+        {
+            warn!(error = %err, request_id = %request.request_id, "failed to edit abandoned permission message");
+        }
+    }
+}
+
+// Claude can die without a SessionEnd (a killed pane, a crashed tmux server),
+// so live sessions are checked against tmux and ended ones archived.
+// Replies to messages sent before routes carried a chat would otherwise never
+// match again once the transport reports the chat they arrive in.
+async fn adopt_legacy_reply_routes(state: &DaemonState) {
+    let Some(chat_id) = state.transport.chat_id() else {
+        return;
+    };
+    match db::adopt_legacy_reply_routes(&state.db, chat_id).await {
+        Ok(0) => {}
+        Ok(count) => info!(
+            count,
+            chat_id, "moved legacy reply routes to the default chat"
+        ),
+        Err(err) => warn!(error = %err, "failed to move legacy reply routes to the default chat"),
+    }
+}
+
+async fn watch_sessions(state: DaemonState) {
+    let period = state.config.session_check_seconds;
+    if period == 0 {
+        return;
+    }
+    let mut ticker = tokio::time::interval(Duration::from_secs(period));
+    loop {
+        tokio::select! {
+            _ = state.shutdown.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+        if let Err(err) = check_sessions(&state).await {
+            warn!(error = %err, "session liveness check failed");
+        }
+    }
+}
+
+async fn check_sessions(state: &DaemonState) -> Result<()> {
+    let now = now_epoch();
+    for (session_id, pane) in db::live_session_panes(&state.db).await? {
+        if pane_alive(&pane).await == Some(false)
+            && db::mark_session_orphaned(&state.db, &session_id, now).await?
+        {
+            info!(session_id = %session_id, pane = %pane, "tmux pane is gone; session orphaned");
+            state.redactor.forget_session(&session_id);
+        }
+    }
+    let cutoff = now - state.config.session_archive_seconds as i64;
+    let archived = db::archive_sessions_ended_before(&state.db, cutoff, now).await?;
+    if archived > 0 {
+        info!(count = archived, "archived ended sessions");
+    }
+    Ok(())
+}
+
+// Only tmux saying it cannot find the pane means the pane is gone. A missing
+// binary, "no server running" or a socket error says nothing about it (the
+// daemon may just be looking at another socket), so those are None.
+async fn pane_alive(tmux_pane: &str) -> Option<bool> {
+    let output = Command::new("tmux")
+        .args(["display-message", "-p", "-t", tmux_pane, "#{pane_id}"])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if output.status.success() {
+        return Some(true);
+    }
+    String::from_utf8_lossy(&output.stderr)
+        .contains("can't find")
+        .then_some(false)
+}
+
+fn build_transport(config: &Config) -> Result<Arc<dyn Transport>> {
+    Ok(match config.transport {
+        TransportKind::Telegram => {
+            // Route chats and approvers are left out on purpose: they are
+            // authorized per request by `route_access`, not for everything.
+            Arc::new(TelegramClient::new(
+                &config.telegram_api_url,
+                config.token()?,
+                config.chat_id()?,
+                config.telegram_authorized_chat_ids.clone(),
+                config.telegram_authorized_user_ids.clone(),
+            ))
+        }
+        TransportKind::Slack => Arc::new(SlackClient::from_config(config)?),
+    })
+}
+
+pub async fn transport_identity(config: &Config) -> Result<String> {
+    match config.transport {
+        TransportKind::Telegram => Ok(format!(
+            "@{}",
+            get_bot_username(&config.telegram_api_url, config.token()?.expose_secret()).await?
+        )),
+        TransportKind::Slack => slack::auth_identity(config).await,
+    }
+}
+
 fn acquire_singleton_lock() -> Result<std::fs::File> {
     let lock_path = config::lock_path()?;
     if let Some(parent) = lock_path.parent() {
@@ -155,328 +388,1656 @@ fn acquire_singleton_lock() -> Result<std::fs::File> {
     }
 }
 
-pub async fn get_bot_username(token: &str) -> Result<String> {
-    let http = Client::new();
-    let url = format!("{TELEGRAM_API}/bot{token}/getMe");
-    let response: TelegramResponse<BotUser> = http.get(url).send().await?.json().await?;
-    if !response.ok {
-        return Err(AppError::TelegramAuthFailed);
+async fn handle_client(stream: UnixStream, state: DaemonState) -> Result<()> {
+    let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
+    while let Some(frame) = framed.next().await {
+        let bytes = frame?;
+        let envelope: HookEnvelope = serde_json::from_slice(&bytes)?;
+        let response = match (envelope.blocking, envelope.hook_event_name.as_str()) {
+            (true, "PermissionRequest") => process_permission_request(&state, &envelope).await?,
+            (true, "PreToolUse") => process_gate(&state, &envelope).await?,
+            _ => {
+                process_async_event(&state, &envelope).await?;
+                continue;
+            }
+        };
+        framed
+            .send(Bytes::from(serde_json::to_vec(&response)?))
+            .await?;
     }
-    let Some(result) = response.result else {
-        return Err(AppError::TelegramAuthFailed);
-    };
-    Ok(result.username.unwrap_or_else(|| "unknown-bot".to_string()))
+    Ok(())
 }
 
-pub async fn wait_for_start_chat(token: &str, max_wait: Duration) -> Result<i64> {
-    let http = Client::new();
-    let mut offset: i64 = 0;
-    let start = tokio::time::Instant::now();
-
-    while start.elapsed() < max_wait {
-        let url = format!("{TELEGRAM_API}/bot{token}/getUpdates");
-        let payload = json!({
-            "timeout": 20,
-            "offset": offset,
-            "allowed_updates": ["message"]
-        });
-        let response: TelegramResponse<Vec<TelegramUpdate>> =
-            http.post(url).json(&payload).send().await?.json().await?;
-
-        if !response.ok {
-            return Err(AppError::TelegramApi(
-                response
-                    .description
-                    .unwrap_or_else(|| "unknown error".to_string()),
-            ));
+async fn process_permission_request(
+    state: &DaemonState,
+    envelope: &HookEnvelope,
+) -> Result<HookResponseEnvelope> {
+    let now = now_epoch();
+    touch_session(state, envelope, Some(SessionStatus::Active), now).await?;
+    let audit = audit_context(state, envelope, now);
+
+    let subject = PermissionSubject::from_payload(&envelope.payload, &envelope.cwd);
+    // A routed class exists to put specific approvers in front of it, so a
+    // policy allow or a learned grant never answers it; a policy deny still does.
+    let route = state.router.route(&subject);
+    let decision = state.policy.evaluate(&subject);
+    let on_timeout = decision.on_timeout;
+    let rule = decision.rule.unwrap_or_default();
+    let hook_output = match decision.action {
+        PolicyAction::Allow if route.is_some() => {
+            info!(request_id = %envelope.request_id, rule = %rule, "policy allow skipped for routed request");
+            None
         }
-
-        for update in response.result.unwrap_or_default() {
-            offset = update.update_id + 1;
-            if let Some(message) = update.message
-                && message.text.as_deref() == Some("/start")
-            {
-                return Ok(message.chat.id);
-            }
+        PolicyAction::Allow => {
+            info!(request_id = %envelope.request_id, rule = %rule, "permission auto-allowed by policy");
+            Some(allow_permission_output())
+        }
+        PolicyAction::Deny => {
+            info!(request_id = %envelope.request_id, rule = %rule, "permission auto-denied by policy");
+            Some(deny_permission_output(&format!(
+                "Denied by policy rule {rule}"
+            )))
         }
+        PolicyAction::Ask => None,
+    };
+    if let Some(hook_output) = hook_output {
+        record_audit(state, &audit, &hook_output, &Decider::Policy(rule)).await;
+        return Ok(HookResponseEnvelope {
+            request_id: envelope.request_id.clone(),
+            hook_output,
+        });
+    }
+
+    if route.is_none()
+        && let Some(grant_id) = find_matching_grant(state, envelope, &subject).await?
+    {
+        info!(request_id = %envelope.request_id, grant_id, "permission auto-allowed by learned grant");
+        let hook_output = allow_permission_output();
+        record_audit(state, &audit, &hook_output, &Decider::Grant(grant_id)).await;
+        return Ok(HookResponseEnvelope {
+            request_id: envelope.request_id.clone(),
+            hook_output,
+        });
     }
 
-    Err(AppError::TelegramPairingTimeout)
+    ask_operator(state, envelope, audit, &subject, None, route, on_timeout).await
 }
 
-async fn handle_client(stream: UnixStream, state: DaemonState) -> Result<()> {
-    let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
-    while let Some(frame) = framed.next().await {
-        let bytes = frame?;
-        let envelope: HookEnvelope = serde_json::from_slice(&bytes)?;
-        if envelope.blocking && envelope.hook_event_name == "PermissionRequest" {
-            let response = process_permission_request(&state, &envelope).await?;
-            framed
-                .send(Bytes::from(serde_json::to_vec(&response)?))
-                .await?;
-            continue;
+// Runs the PreToolUse deny-list. Calls that match nothing get an empty
+// response so Claude Code's own permission flow still applies.
+async fn process_gate(
+    state: &DaemonState,
+    envelope: &HookEnvelope,
+) -> Result<HookResponseEnvelope> {
+    let subject = PermissionSubject::from_payload(&envelope.payload, &envelope.cwd);
+    let Some(reason) = state.gate.evaluate(&subject) else {
+        return Ok(HookResponseEnvelope {
+            request_id: envelope.request_id.clone(),
+            hook_output: json!({}),
+        });
+    };
+
+    let now = now_epoch();
+    touch_session(state, envelope, Some(SessionStatus::Active), now).await?;
+    let audit = audit_context(state, envelope, now);
+    let permission = match state.config.gate.on_match {
+        GateAction::Block => {
+            info!(request_id = %envelope.request_id, reason = %reason, "tool call blocked by gate");
+            let hook_output =
+                deny_permission_output(&format!("Blocked by codelatch gate: {reason}"));
+            record_audit(state, &audit, &hook_output, &Decider::Gate).await;
+            let notice = format!(
+                "*⛔ Gate* · {}\n\nBlocked {}: {}",
+                md_inline_code(&envelope.session_name),
+                md_inline_code(subject.tool_name),
+                md_escape_text(&reason)
+            );
+            if let Err(err) = state.transport.send_markdown(&notice).await {
+                warn!(error = %err, request_id = %envelope.request_id, "failed to send gate notice");
+            }
+            hook_output
         }
-        process_async_event(&state, &envelope).await?;
-    }
-    Ok(())
+        GateAction::Escalate => {
+            let route = state.router.route(&subject);
+            ask_operator(state, envelope, audit, &subject, Some(&reason), route, None)
+                .await?
+                .hook_output
+        }
+    };
+    Ok(HookResponseEnvelope {
+        request_id: envelope.request_id.clone(),
+        hook_output: pre_tool_use_output(&permission),
+    })
 }
 
-async fn process_permission_request(
+async fn ask_operator(
     state: &DaemonState,
     envelope: &HookEnvelope,
+    audit: AuditContext,
+    subject: &PermissionSubject<'_>,
+    gate_reason: Option<&str>,
+    route: Option<Route>,
+    rule_timeout: Option<TimeoutOutcome>,
 ) -> Result<HookResponseEnvelope> {
     let now = now_epoch();
     let expires_at = now + state.config.auto_deny_seconds as i64;
-    db::upsert_session(&state.db, envelope, now).await?;
-    db::insert_pending_request(&state.db, envelope, expires_at, now).await?;
-
-    let command = extract_command(envelope);
-    let redacted_command = state.redactor.redact(&command);
-    let message_id = state
-        .telegram
-        .send_permission_message(
-            &envelope.session_name,
-            &redacted_command,
-            &envelope.cwd,
+    db::insert_pending_request(
+        &state.db,
+        envelope,
+        &audit.tool_name,
+        &audit.tool_input,
+        expires_at,
+        now,
+    )
+    .await?;
+
+    let transport = route_transport(state, route.as_ref());
+    if let Some(route) = &route {
+        db::set_pending_route(
+            &state.db,
             &envelope.request_id,
-            state.config.auto_deny_seconds,
+            &route.name,
+            route.chat_id,
+            route.required_approvals,
         )
         .await?;
-    db::set_pending_message_id(&state.db, &envelope.request_id, message_id).await?;
+    }
+
+    let preview = render::render_permission(
+        &envelope.payload,
+        &state.redactor,
+        Some(&envelope.session_id),
+    );
+    let on_timeout =
+        resolve_timeout_outcome(state, &envelope.session_id, rule_timeout, route.is_some()).await;
+    let prompt = permission_prompt(
+        state,
+        envelope,
+        &preview,
+        gate_reason,
+        route.as_ref(),
+        on_timeout,
+    );
+
+    // A digest line only shows the tool and its subject. Routed requests need
+    // their own approvers, and a diff, file content or attachment would never
+    // reach the operator, so those prompts are always sent on their own.
+    let batchable = route.is_none()
+        && !prompt.spill
+        && !matches!(
+            audit.tool_name.as_str(),
+            "Edit" | "MultiEdit" | "Write" | "NotebookEdit"
+        );
 
+    // Register the waiter before the prompt goes out so a button tapped the
+    // instant it arrives is not mistaken for a stale request.
     let (tx, rx) = oneshot::channel::<HookResponseEnvelope>();
-    {
-        let mut pending = state.pending_waiters.lock().await;
-        pending.insert(envelope.request_id.clone(), tx);
-    }
+    state.pending_waiters.lock().await.insert(
+        envelope.request_id.clone(),
+        PendingWaiter {
+            sender: tx,
+            audit: audit.clone(),
+            grant: GrantPattern::derive(subject),
+            cwd: envelope.cwd.clone(),
+            subject: subject.subject.map(str::to_string),
+            tmux_pane: envelope.tmux_pane.clone(),
+            rule_timeout,
+            message_id: 0,
+            transport: transport.clone(),
+            route: route.clone(),
+            approvals: Vec::new(),
+            batchable,
+            batch: None,
+            prompt: prompt.clone(),
+            window_start: now,
+            expires_at,
+            reminders_sent: 0,
+            awaiting_note: false,
+            edit_lock: Arc::new(Mutex::new(())),
+        },
+    );
 
-    let timeout_state = state.clone();
-    let timeout_request_id = envelope.request_id.clone();
-    tokio::spawn(async move {
-        tokio::select! {
-            _ = timeout_state.shutdown.cancelled() => {}
-            _ = tokio::time::sleep(Duration::from_secs(timeout_state.config.auto_deny_seconds)) => {
-                if let Ok(changed) =
-                    db::transition_pending_state(&timeout_state.db, &timeout_request_id, "timed_out").await
-                    && changed
-                {
-                    let _ = timeout_state
-                        .telegram
-                        .edit_message(message_id, "🔴 Permission\n\n⏳ Timed out — denied")
-                        .await;
-                    let hook_output = deny_permission_output("Denied by timeout");
-                    complete_waiter(&timeout_state, &timeout_request_id, hook_output).await;
-                }
+    // Prompts go out one at a time so a burst from one session always sees
+    // the message ids of the prompts before it and can fold them into a batch.
+    let sent = {
+        let _turn = state.prompt_lock.lock().await;
+        let joined = if batchable {
+            join_batch(state, &envelope.request_id, &envelope.session_id).await
+        } else {
+            Ok(None)
+        };
+        let sent = match joined {
+            Ok(Some(message_id)) => Ok(message_id),
+            Ok(None) => {
+                send_permission_message(
+                    state,
+                    transport.as_ref(),
+                    &envelope.session_name,
+                    &preview,
+                    &prompt,
+                )
+                .await
             }
+            Err(err) => Err(err),
+        };
+        if let Ok(message_id) = sent
+            && let Some(waiter) = state
+                .pending_waiters
+                .lock()
+                .await
+                .get_mut(&envelope.request_id)
+        {
+            waiter.message_id = message_id;
         }
+        sent
+    };
+    let message_id = match sent {
+        Ok(message_id) => message_id,
+        Err(err) => {
+            state
+                .pending_waiters
+                .lock()
+                .await
+                .remove(&envelope.request_id);
+            return Err(err);
+        }
+    };
+    db::set_pending_message_id(&state.db, &envelope.request_id, message_id).await?;
+
+    let countdown_state = state.clone();
+    let countdown_request_id = envelope.request_id.clone();
+    tokio::spawn(async move {
+        run_countdown(countdown_state, countdown_request_id).await;
     });
 
     match rx.await {
         Ok(response) => Ok(response),
-        Err(_) => Ok(HookResponseEnvelope {
-            request_id: envelope.request_id.clone(),
-            hook_output: deny_permission_output("Denied because daemon waiter closed"),
-        }),
+        Err(_) => {
+            let hook_output = deny_permission_output("Denied because daemon waiter closed");
+            record_audit(state, &audit, &hook_output, &Decider::DaemonClosed).await;
+            Ok(HookResponseEnvelope {
+                request_id: envelope.request_id.clone(),
+                hook_output,
+            })
+        }
     }
 }
 
-async fn process_async_event(state: &DaemonState, envelope: &HookEnvelope) -> Result<()> {
-    db::upsert_session(&state.db, envelope, now_epoch()).await?;
+// A route with its own chat posts through a transport bound to that chat;
+// everything else goes to the default chat.
+fn route_transport(state: &DaemonState, route: Option<&Route>) -> Arc<dyn Transport> {
+    let Some((name, chat_id)) = route.and_then(|route| Some((&route.name, route.chat_id?))) else {
+        return state.transport.clone();
+    };
+    state.transport.to_chat(chat_id).unwrap_or_else(|| {
+        warn!(route = %name, "transport cannot post to route chat; using the default chat");
+        state.transport.clone()
+    })
+}
 
-    let payload_pretty = serde_json::to_string_pretty(&envelope.payload)?;
-    let redacted_payload = state.redactor.redact(&payload_pretty);
-    let tmux_context =
-        capture_context(envelope.tmux_pane.as_deref(), state.config.context_lines).await;
-    let redacted_context = tmux_context
-        .as_ref()
-        .map(|value| state.redactor.redact(value));
-    let markdown = format_async_markdown(envelope, &redacted_payload, redacted_context.as_deref());
+#[derive(Clone)]
+struct PermissionPrompt {
+    body: String,
+    buttons: Vec<Vec<Button>>,
+    spill: bool,
+    on_timeout: TimeoutOutcome,
+    required_approvals: usize,
+    approved_by: Vec<String>,
+}
 
-    let message_id = if markdown.chars().count() <= MAX_TELEGRAM_TEXT {
-        state.telegram.send_markdown(&markdown).await?
-    } else {
+impl PermissionPrompt {
+    fn markdown(&self, remaining_seconds: i64) -> String {
+        let remaining = remaining_seconds.max(0);
+        let mut text = self.body.clone();
+        if !self.approved_by.is_empty() {
+            text.push_str(&format!(
+                "\n\n{}",
+                md_escape_text(&format!(
+                    "Approvals {}/{}: {}",
+                    self.approved_by.len(),
+                    self.required_approvals,
+                    self.approved_by.join(", ")
+                ))
+            ));
+        }
+        format!(
+            "{text}\n\n{} {}",
+            timeout_label(self.on_timeout),
+            md_escape_text(&format_countdown(remaining))
+        )
+    }
+}
+
+fn timeout_label(outcome: TimeoutOutcome) -> &'static str {
+    match outcome {
+        TimeoutOutcome::Deny => "Auto deny in",
+        TimeoutOutcome::Allow => "Auto allow in",
+        TimeoutOutcome::Pause => "Auto deny and pause in",
+    }
+}
+
+// A rule's outcome describes the tool class, so it beats the per-session
+// override, which in turn beats the global `on_timeout`. A routed request
+// exists to wait for its approvers, so nothing lets it through unanswered.
+async fn resolve_timeout_outcome(
+    state: &DaemonState,
+    session_id: &str,
+    rule_timeout: Option<TimeoutOutcome>,
+    routed: bool,
+) -> TimeoutOutcome {
+    if routed {
+        return TimeoutOutcome::Deny;
+    }
+    if let Some(outcome) = rule_timeout {
+        return outcome;
+    }
+    let session = db::get_session_timeout_outcome(&state.db, session_id)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|value| TimeoutOutcome::parse(&value));
+    session.unwrap_or(state.config.on_timeout)
+}
+
+fn format_countdown(seconds: i64) -> String {
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn permission_prompt(
+    state: &DaemonState,
+    envelope: &HookEnvelope,
+    preview: &PermissionPreview,
+    gate_reason: Option<&str>,
+    route: Option<&Route>,
+    on_timeout: TimeoutOutcome,
+) -> PermissionPrompt {
+    let (title, mut matched) = match gate_reason {
+        Some(reason) => (
+            "⛔ Gate",
+            format!("\n\n*Matched* {}", md_escape_text(reason)),
+        ),
+        None => ("🔴 Permission", String::new()),
+    };
+    let request_id = &envelope.request_id;
+    if let Some(route) = route {
+        matched.push_str(&format!("\n\n*Route* {}", md_inline_code(&route.name)));
+        if route.required_approvals > 1 {
+            matched.push_str(&md_escape_text(&format!(
+                " · {} approvals needed",
+                route.required_approvals
+            )));
+        }
+    }
+    let format_body = |detail: &str| {
+        format!(
+            "*{title}* · {}\n\n{}{matched}\n\n*Dir* {}",
+            md_inline_code(&envelope.session_name),
+            detail,
+            md_inline_code(&envelope.cwd),
+        )
+    };
+
+    let mut first_row = vec![
+        Button::new("Allow", format!("permit:{request_id}:allow")),
+        Button::new("Deny", format!("permit:{request_id}:deny")),
+    ];
+    // Note replies are matched to prompts by message id, which is only unique
+    // within the default chat.
+    if route.is_none_or(|route| route.chat_id.is_none()) {
+        first_row.push(Button::new(
+            "Deny + note",
+            format!("permit:{request_id}:deny_note"),
+        ));
+    }
+    let mut buttons = vec![first_row];
+    // Learned grants only short-circuit PermissionRequest, so offering them on
+    // a gate escalation would promise something the gate never honours. Routed
+    // requests never consult grants at all.
+    if gate_reason.is_none() && route.is_none() {
+        buttons.push(vec![
+            Button::new(
+                "Always (session)",
+                format!("permit:{request_id}:allow_session"),
+            ),
+            Button::new("Always (repo)", format!("permit:{request_id}:allow_repo")),
+        ]);
+    }
+    if state.config.extend_seconds > 0 {
+        buttons.push(vec![Button::new(
+            format!("Extend +{}", format_duration(state.config.extend_seconds)),
+            format!("permit:{request_id}:extend"),
+        )]);
+    }
+
+    let mut prompt = PermissionPrompt {
+        body: format_body(&preview.markdown(&preview.detail)),
+        buttons,
+        spill: false,
+        on_timeout,
+        required_approvals: route.map_or(1, |route| route.required_approvals),
+        approved_by: Vec::new(),
+    };
+    let full = prompt.markdown(state.config.auto_deny_seconds as i64);
+    if full.chars().count() > state.transport.max_text_chars() {
+        let truncated = truncate_head(&preview.detail, PERMISSION_PREVIEW_CHARS);
+        prompt.body = format_body(&format!(
+            "{}\n_Full preview attached_",
+            preview.markdown(&truncated)
+        ));
+        prompt.spill = true;
+    } else if preview.full.is_some() {
+        // The inline excerpt fits, but the rest is only in the attachment.
+        prompt.body = format_body(&format!(
+            "{}\n_Full content attached_",
+            preview.markdown(&preview.detail)
+        ));
+        prompt.spill = true;
+    }
+    prompt
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+async fn send_permission_message(
+    state: &DaemonState,
+    transport: &dyn Transport,
+    session_name: &str,
+    preview: &PermissionPreview,
+    prompt: &PermissionPrompt,
+) -> Result<i64> {
+    let text = prompt.markdown(state.config.auto_deny_seconds as i64);
+    let message_id = transport.ask(&text, &prompt.buttons).await?;
+    if prompt.spill {
         let file_name = format!(
-            "{}-{}-event.txt",
-            safe_filename(&envelope.session_name),
-            safe_filename(&envelope.hook_event_name.to_lowercase())
-        );
-        let mut text = format!(
-            "{} {} · {}\n\n{}",
-            icon_for(envelope),
-            envelope.hook_event_name,
-            envelope.session_name,
-            redacted_payload
+            "{}-{}",
+            safe_filename(session_name),
+            preview.attachment_name
         );
-        if let Some(context) = redacted_context {
-            text.push_str("\n\nContext:\n");
-            text.push_str(&context);
-        }
-        state
-            .telegram
+        let caption = format!("*🔴 Permission preview* · {}", md_inline_code(session_name));
+        transport
             .send_document(
                 &file_name,
-                text.into_bytes(),
-                Some(&format!(
-                    "*{}* · {}",
-                    md_escape_text(&event_title(envelope)),
-                    md_inline_code(&envelope.session_name)
-                )),
+                preview.attachment().as_bytes().to_vec(),
+                Some(&caption),
             )
-            .await?
-    };
-    if envelope.hook_event_name == "Notification" {
-        db::insert_reply_route(&state.db, message_id, envelope, now_epoch()).await?;
+            .await?;
     }
-
-    Ok(())
+    Ok(message_id)
 }
 
-async fn long_poll_loop(state: DaemonState) -> Result<()> {
-    let mut offset: i64 = 0;
+// One task per pending request: it keeps the "Auto deny in" line current,
+// sends reminders as the deadline approaches and applies the timeout outcome
+// once it passes. The deadline lives on the waiter so the Extend button can
+// move it.
+async fn run_countdown(state: DaemonState, request_id: String) {
+    let mut last_update = now_epoch();
     loop {
-        let updates = tokio::select! {
-            _ = state.shutdown.cancelled() => return Ok(()),
-            updates = state.telegram.get_updates(offset) => updates?,
+        tokio::select! {
+            _ = state.shutdown.cancelled() => return,
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+        let now = now_epoch();
+        let (expires_at, message_id, reminder) = {
+            let mut waiters = state.pending_waiters.lock().await;
+            let Some(waiter) = waiters.get_mut(&request_id) else {
+                return;
+            };
+            let crossed = reminders_crossed(
+                &state.config.reminder_percents,
+                waiter.window_start,
+                waiter.expires_at,
+                now,
+            );
+            let reminder =
+                (crossed > waiter.reminders_sent && now < waiter.expires_at).then(|| {
+                    (
+                        waiter.audit.session_name.clone(),
+                        waiter.expires_at - now,
+                        waiter.prompt.on_timeout,
+                        waiter.batch.clone(),
+                        waiter.transport.clone(),
+                    )
+                });
+            waiter.reminders_sent = waiter.reminders_sent.max(crossed);
+            (waiter.expires_at, waiter.message_id, reminder)
         };
-        for update in updates {
-            offset = update.update_id + 1;
 
-            if let Some(callback) = update.callback_query {
-                if let Err(err) = handle_callback_query(&state, callback).await {
-                    warn!(error = %err, "failed processing callback query");
-                }
+        if now >= expires_at {
+            time_out_permission(&state, &request_id, message_id).await;
+            return;
+        }
+
+        if let Some((session_name, remaining, on_timeout, batch, transport)) = reminder {
+            if let Some(batch_id) = batch
+                && batch_lead(&state, &batch_id).await.as_deref() != Some(request_id.as_str())
+            {
                 continue;
             }
-            if let Some(message) = update.message
-                && let Err(err) = handle_message(&state, message).await
-            {
-                warn!(error = %err, "failed processing telegram message");
+            let verb = match on_timeout {
+                TimeoutOutcome::Allow => "auto-allows",
+                TimeoutOutcome::Deny | TimeoutOutcome::Pause => "auto-denies",
+            };
+            let text = format!(
+                "*⏰ Still waiting* · {}\n\n{}",
+                md_inline_code(&session_name),
+                md_escape_text(&format!(
+                    "A permission request {verb} in {}. Scroll up to answer it.",
+                    format_countdown(remaining)
+                ))
+            );
+            if let Err(err) = transport.send_markdown(&text).await {
+                warn!(error = %err, request_id = %request_id, "failed to send permission reminder");
             }
         }
-    }
-}
 
-async fn handle_message(state: &DaemonState, message: TelegramMessage) -> Result<()> {
-    if message.chat.id != state.telegram.chat_id {
-        return Ok(());
+        let interval = state.config.countdown_update_seconds as i64;
+        if interval > 0 && now - last_update >= interval {
+            last_update = now;
+            refresh_countdown(&state, &request_id).await;
+        }
     }
+}
 
-    let Some(text) = message.text.clone() else {
-        return Ok(());
+async fn time_out_permission(state: &DaemonState, request_id: &str, message_id: i64) {
+    let Some((session_id, rule_timeout, routed, tmux_pane)) = state
+        .pending_waiters
+        .lock()
+        .await
+        .get(request_id)
+        .map(|waiter| {
+            (
+                waiter.audit.session_id.clone(),
+                waiter.rule_timeout,
+                waiter.route.is_some(),
+                waiter.tmux_pane.clone(),
+            )
+        })
+    else {
+        return;
     };
-
-    if text.starts_with("/peek") {
-        handle_peek_command(state, &message).await?;
-        return Ok(());
+    // Re-resolved here so an /ontimeout sent while the prompt was up counts.
+    let outcome = resolve_timeout_outcome(state, &session_id, rule_timeout, routed).await;
+    let (next_state, status, hook_output) = match outcome {
+        TimeoutOutcome::Deny => (
+            "timed_out",
+            "⏳ Timed out — denied",
+            deny_permission_output("Denied by timeout"),
+        ),
+        TimeoutOutcome::Allow => (
+            "timed_out_allowed",
+            "⏳ Timed out — allowed",
+            allow_permission_output(),
+        ),
+        TimeoutOutcome::Pause => (
+            "timed_out_paused",
+            "⏳ Timed out — denied, session paused",
+            deny_permission_output(
+                "Denied by timeout. The session was paused until the operator returns.",
+            ),
+        ),
+    };
+    if !db::transition_pending_state(&state.db, request_id, next_state)
+        .await
+        .unwrap_or(false)
+    {
+        return;
     }
+    edit_permission_message(state, request_id, message_id, status).await;
+    complete_waiter(state, request_id, hook_output, Decider::Timeout).await;
 
-    if text.starts_with("/diff") {
-        handle_diff_command(state, &message).await?;
-        return Ok(());
+    // Escape stops Claude Code from acting on the denial and retrying, which
+    // is what an unattended session would otherwise do until morning.
+    if outcome == TimeoutOutcome::Pause {
+        let paused = match tmux_pane.as_deref() {
+            Some(pane) => send_keys(pane, "Escape").await,
+            None => false,
+        };
+        if !paused {
+            warn!(request_id = %request_id, "failed to pause session after permission timeout");
+        }
     }
+}
+
+fn reminders_crossed(percents: &[u8], window_start: i64, expires_at: i64, now: i64) -> usize {
+    let window = (expires_at - window_start).max(1);
+    let elapsed = now - window_start;
+    percents
+        .iter()
+        .filter(|percent| elapsed * 100 >= i64::from(**percent) * window)
+        .count()
+}
 
-    if text.starts_with("/log") {
-        handle_log_command(state, &message).await?;
+async fn refresh_countdown(state: &DaemonState, request_id: &str) {
+    let batch = state
+        .pending_waiters
+        .lock()
+        .await
+        .get(request_id)
+        .and_then(|waiter| waiter.batch.clone());
+    if let Some(batch_id) = batch {
+        // Every item has its own countdown task; only the first live one
+        // re-renders the digest so it is edited once per interval.
+        if batch_lead(state, &batch_id).await.as_deref() == Some(request_id) {
+            refresh_batch(state, &batch_id).await;
+        }
+        return;
+    }
+    let view = state
+        .pending_waiters
+        .lock()
+        .await
+        .get(request_id)
+        .filter(|waiter| !waiter.awaiting_note && waiter.message_id != 0)
+        .map(|waiter| {
+            (
+                waiter.edit_lock.clone(),
+                waiter.transport.clone(),
+                waiter.message_id,
+                waiter.prompt.clone(),
+                waiter.expires_at,
+            )
+        });
+    let Some((lock, transport, message_id, prompt, expires_at)) = view else {
+        return;
+    };
+    let _guard = lock.lock().await;
+    // A decision moves the row out of `waiting` before its final edit takes
+    // this lock, so checking here keeps a stale countdown from landing last.
+    if !db::is_pending_waiting(&state.db, request_id)
+        .await
+        .unwrap_or(false)
+    {
+        return;
+    }
+    let text = prompt.markdown(expires_at - now_epoch());
+    if let Err(err) = transport.edit_ask(message_id, &text, &prompt.buttons).await {
+        warn!(error = %err, request_id = %request_id, "failed to update permission countdown");
+    }
+}
+
+async fn edit_permission_message(
+    state: &DaemonState,
+    request_id: &str,
+    message_id: i64,
+    status: &str,
+) {
+    let (lock, batch, transport) = state
+        .pending_waiters
+        .lock()
+        .await
+        .get(request_id)
+        .map(|waiter| {
+            (
+                Some(waiter.edit_lock.clone()),
+                waiter.batch.clone(),
+                waiter.transport.clone(),
+            )
+        })
+        .unwrap_or_else(|| (None, None, state.transport.clone()));
+    if let Some(batch_id) = batch {
+        if let Some(batch) = state.batches.lock().await.get_mut(&batch_id) {
+            batch.set_status(request_id, status);
+        }
+        refresh_batch(state, &batch_id).await;
+        return;
+    }
+    let _guard = match &lock {
+        Some(lock) => Some(lock.lock().await),
+        None => None,
+    };
+    let _ = transport
+        .edit_text(message_id, &format!("🔴 Permission\n\n{status}"))
+        .await;
+}
+
+// Folds a new prompt into a digest when the same session already has prompts
+// on screen. Returns the digest's message id, or None to send it on its own.
+async fn join_batch(
+    state: &DaemonState,
+    request_id: &str,
+    session_id: &str,
+) -> Result<Option<i64>> {
+    if !state.config.batch_permissions {
+        return Ok(None);
+    }
+    let existing = state
+        .batches
+        .lock()
+        .await
+        .iter()
+        .find(|(_, batch)| batch.session_id == session_id)
+        .map(|(batch_id, batch)| (batch_id.clone(), batch.message_id, batch.edit_lock.clone()));
+    if let Some((batch_id, message_id, edit_lock)) = existing {
+        let item = attach_to_batch(state, request_id, &batch_id, message_id, edit_lock).await;
+        if let Some(batch) = state.batches.lock().await.get_mut(&batch_id) {
+            batch.items.extend(item);
+        }
+        refresh_batch(state, &batch_id).await;
+        return Ok(Some(message_id));
+    }
+
+    let siblings = state
+        .pending_waiters
+        .lock()
+        .await
+        .iter()
+        .filter(|(id, waiter)| {
+            id.as_str() != request_id
+                && waiter.audit.session_id == session_id
+                && waiter.message_id != 0
+                && waiter.batch.is_none()
+                && waiter.batchable
+                && !waiter.awaiting_note
+        })
+        .map(|(id, waiter)| (id.clone(), waiter.message_id, waiter.audit.requested_at))
+        .collect::<Vec<_>>();
+    if siblings.is_empty() {
+        return Ok(None);
+    }
+    let mut siblings = siblings;
+    siblings.sort_by_key(|(id, _, requested_at)| (*requested_at, id.clone()));
+
+    let batch_id = siblings[0].0.clone();
+    let edit_lock = Arc::new(Mutex::new(()));
+    let mut items = Vec::with_capacity(siblings.len() + 1);
+    for request_id in siblings
+        .iter()
+        .map(|(id, _, _)| id.as_str())
+        .chain([request_id])
+    {
+        items.extend(attach_to_batch(state, request_id, &batch_id, 0, edit_lock.clone()).await);
+    }
+    let session_name = match state.pending_waiters.lock().await.get(request_id) {
+        Some(waiter) => waiter.audit.session_name.clone(),
+        None => return Ok(None),
+    };
+    let mut batch = PermissionBatch {
+        session_id: session_id.to_string(),
+        session_name,
+        message_id: 0,
+        items,
+        extendable: state.config.extend_seconds > 0,
+        edit_lock,
+    };
+    let live = live_items(state, &batch_id).await;
+    let (text, buttons) = batch.render(&batch_id, now_epoch(), &live);
+    let message_id = state.transport.ask(&text, &buttons).await?;
+    batch.message_id = message_id;
+    state.batches.lock().await.insert(batch_id.clone(), batch);
+
+    let mut waiters = state.pending_waiters.lock().await;
+    for waiter in waiters
+        .values_mut()
+        .filter(|waiter| waiter.batch.as_deref() == Some(&batch_id))
+    {
+        waiter.message_id = message_id;
+    }
+    drop(waiters);
+    for (request_id, old_message_id, _) in &siblings {
+        if let Err(err) = state
+            .transport
+            .edit_text(
+                *old_message_id,
+                "🔴 Permission\n\n📋 Moved to the batch below.",
+            )
+            .await
+        {
+            warn!(error = %err, request_id = %request_id, "failed to retire batched permission prompt");
+        }
+        if let Err(err) = db::set_pending_message_id(&state.db, request_id, message_id).await {
+            warn!(error = %err, request_id = %request_id, "failed to record batch message id");
+        }
+    }
+    Ok(Some(message_id))
+}
+
+async fn attach_to_batch(
+    state: &DaemonState,
+    request_id: &str,
+    batch_id: &str,
+    message_id: i64,
+    edit_lock: Arc<Mutex<()>>,
+) -> Option<BatchItem> {
+    let mut waiters = state.pending_waiters.lock().await;
+    let waiter = waiters.get_mut(request_id)?;
+    waiter.batch = Some(batch_id.to_string());
+    waiter.message_id = message_id;
+    waiter.edit_lock = edit_lock;
+    Some(BatchItem::new(
+        request_id,
+        &waiter.audit.tool_name,
+        waiter.subject.as_deref(),
+        &state.redactor,
+        &waiter.audit.session_id,
+    ))
+}
+
+async fn live_items(state: &DaemonState, batch_id: &str) -> LiveItems {
+    state
+        .pending_waiters
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, waiter)| waiter.batch.as_deref() == Some(batch_id))
+        .map(|(id, waiter)| (id.clone(), (waiter.expires_at, waiter.prompt.on_timeout)))
+        .collect()
+}
+
+async fn batch_lead(state: &DaemonState, batch_id: &str) -> Option<String> {
+    let live = live_items(state, batch_id).await;
+    state
+        .batches
+        .lock()
+        .await
+        .get(batch_id)?
+        .live_requests(&live)
+        .into_iter()
+        .next()
+}
+
+// Re-renders a digest from its items. Once nothing in it is live the buttons
+// go away and the batch is dropped, so the session's next burst starts fresh.
+async fn refresh_batch(state: &DaemonState, batch_id: &str) {
+    let Some(lock) = state
+        .batches
+        .lock()
+        .await
+        .get(batch_id)
+        .map(|batch| batch.edit_lock.clone())
+    else {
+        return;
+    };
+    let _guard = lock.lock().await;
+    let live = live_items(state, batch_id).await;
+    let rendered = {
+        let mut batches = state.batches.lock().await;
+        let Some(batch) = batches.get(batch_id) else {
+            return;
+        };
+        let (text, buttons) = batch.render(batch_id, now_epoch(), &live);
+        let message_id = batch.message_id;
+        let done = buttons.is_empty();
+        if done {
+            batches.remove(batch_id);
+        }
+        (message_id, text, buttons)
+    };
+    let (message_id, text, buttons) = rendered;
+    if let Err(err) = state.transport.edit_ask(message_id, &text, &buttons).await {
+        warn!(error = %err, batch_id = %batch_id, "failed to update permission batch");
+    }
+}
+
+async fn resolve_batch(
+    state: &DaemonState,
+    batch_id: &str,
+    action: &str,
+    actor: Option<&Actor>,
+) -> Result<()> {
+    let live = live_items(state, batch_id).await;
+    let Some(requests) = state
+        .batches
+        .lock()
+        .await
+        .get(batch_id)
+        .map(|batch| batch.live_requests(&live))
+    else {
         return Ok(());
+    };
+    for request_id in requests {
+        let (next_state, status, hook_output) = match action {
+            "allow" => ("approved", "✅ Approved", allow_permission_output()),
+            "deny" => (
+                "denied",
+                "❌ Denied",
+                deny_permission_output("Denied by remote operator"),
+            ),
+            _ => return Ok(()),
+        };
+        if !db::transition_pending_state(&state.db, &request_id, next_state).await? {
+            continue;
+        }
+        if let Some(batch) = state.batches.lock().await.get_mut(batch_id) {
+            batch.set_status(&request_id, &signed(status, actor));
+        }
+        complete_waiter(
+            state,
+            &request_id,
+            hook_output,
+            Decider::Operator(actor.cloned()),
+        )
+        .await;
     }
+    refresh_batch(state, batch_id).await;
+    Ok(())
+}
 
-    if text.starts_with("/sessions") {
-        let sessions = db::list_sessions(&state.db).await?;
-        let default = db::get_default_route(&state.db).await?;
-        if sessions.is_empty() {
-            state.telegram.send_message("No active sessions.").await?;
-        } else {
-            let mut out = String::from("Active sessions:\n");
-            for s in sessions {
-                let marker = default
-                    .as_ref()
-                    .is_some_and(|route| route.session_id == s.session_id);
-                let prefix = if marker { "* " } else { "- " };
-                out.push_str(&format!("{prefix}{} ({})\n", s.name, s.session_id));
+async fn extend_permission(state: &DaemonState, request_id: &str) -> Result<()> {
+    let now = now_epoch();
+    let (extended, transport, batch) = {
+        let mut waiters = state.pending_waiters.lock().await;
+        let Some(waiter) = waiters.get_mut(request_id) else {
+            return Ok(());
+        };
+        let transport = waiter.transport.clone();
+        let batch = waiter.batch.clone();
+        // Claude Code abandons the hook at its own timeout, so the deadline
+        // has to stay comfortably inside it.
+        let limit = waiter.audit.requested_at + state.config.hook_timeout_seconds as i64
+            - HOOK_TIMEOUT_MARGIN_SECONDS;
+        let expires_at = (waiter.expires_at + state.config.extend_seconds as i64).min(limit);
+        let extended = (expires_at > waiter.expires_at).then(|| {
+            waiter.expires_at = expires_at;
+            waiter.window_start = now;
+            waiter.reminders_sent = 0;
+            expires_at
+        });
+        (extended, transport, batch)
+    };
+    let Some(expires_at) = extended else {
+        transport
+            .send_text("This request is already at the hook timeout and cannot be extended.")
+            .await?;
+        return Ok(());
+    };
+    db::set_pending_expiry(&state.db, request_id, expires_at).await?;
+    // Only the lead item re-renders a digest on its ticks, so an extended
+    // item further down redraws it straight away.
+    match batch {
+        Some(batch_id) => refresh_batch(state, &batch_id).await,
+        None => refresh_countdown(state, request_id).await,
+    }
+    Ok(())
+}
+
+async fn find_matching_grant(
+    state: &DaemonState,
+    envelope: &HookEnvelope,
+    subject: &PermissionSubject<'_>,
+) -> Result<Option<i64>> {
+    let grants = db::find_grants_for(
+        &state.db,
+        &envelope.session_id,
+        &envelope.cwd,
+        subject.tool_name,
+    )
+    .await?;
+    Ok(grants
+        .iter()
+        .find(|record| {
+            GrantPattern::from_record(record).is_some_and(|grant| grant.matches(subject))
+        })
+        .map(|record| record.id))
+}
+
+async fn persist_grant(
+    state: &DaemonState,
+    request_id: &str,
+    scope: &str,
+) -> Result<Option<GrantPattern>> {
+    let (grant, scope_value) = {
+        let waiters = state.pending_waiters.lock().await;
+        let Some(waiter) = waiters.get(request_id) else {
+            return Ok(None);
+        };
+        let scope_value = match scope {
+            "session" => waiter.audit.session_id.clone(),
+            _ => waiter.cwd.clone(),
+        };
+        (waiter.grant.clone(), scope_value)
+    };
+    let record = db::NewGrant {
+        scope: scope.to_string(),
+        scope_value,
+        tool_name: grant.tool_name.clone(),
+        match_kind: grant.kind.as_str().to_string(),
+        pattern: grant.pattern.clone(),
+        request_id: request_id.to_string(),
+    };
+    let id = db::insert_grant(&state.db, &record, now_epoch()).await?;
+    info!(request_id = %request_id, grant_id = id, scope = %scope, "stored learned grant");
+    Ok(Some(grant))
+}
+
+fn audit_context(state: &DaemonState, envelope: &HookEnvelope, now: i64) -> AuditContext {
+    let tool_name = envelope
+        .payload
+        .get("tool_name")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+        .to_string();
+    let tool_input = envelope
+        .payload
+        .get("tool_input")
+        .map(Value::to_string)
+        .unwrap_or_default();
+    AuditContext {
+        request_id: envelope.request_id.clone(),
+        session_id: envelope.session_id.clone(),
+        session_name: envelope.session_name.clone(),
+        tool_name,
+        tool_input: state
+            .redactor
+            .redact(&tool_input, Some(&envelope.session_id)),
+        requested_at: now,
+        started: Instant::now(),
+    }
+}
+
+async fn record_audit(
+    state: &DaemonState,
+    audit: &AuditContext,
+    hook_output: &Value,
+    decider: &Decider,
+) {
+    let decision = hook_output
+        .pointer("/hookSpecificOutput/decision/behavior")
+        .and_then(Value::as_str)
+        .unwrap_or("deny")
+        .to_string();
+    let entry = db::NewAuditEntry {
+        request_id: audit.request_id.clone(),
+        session_id: audit.session_id.clone(),
+        session_name: audit.session_name.clone(),
+        tool_name: audit.tool_name.clone(),
+        tool_input: audit.tool_input.clone(),
+        decision,
+        decider: decider.label(),
+        decided_by: decider.actor(),
+        latency_ms: audit.started.elapsed().as_millis() as i64,
+        requested_at: audit.requested_at,
+        decided_at: now_epoch(),
+    };
+    if let Err(err) = db::insert_audit_entry(&state.db, &entry).await {
+        warn!(error = %err, request_id = %audit.request_id, "failed to write permission audit entry");
+    }
+}
+
+async fn process_async_event(state: &DaemonState, envelope: &HookEnvelope) -> Result<()> {
+    let status = SessionStatus::after_event(&envelope.hook_event_name);
+    touch_session(state, envelope, status, now_epoch()).await?;
+    if envelope.hook_event_name == "SessionStart" && state.config.redaction.session_secrets {
+        load_session_secrets(
+            state,
+            &envelope.session_id,
+            &envelope.cwd,
+            envelope.secrets.clone(),
+        )
+        .await;
+    }
+
+    let payload_pretty = serde_json::to_string_pretty(&envelope.payload)?;
+    let redacted_payload = state
+        .redactor
+        .redact(&payload_pretty, Some(&envelope.session_id));
+    let tmux_context =
+        capture_context(envelope.tmux_pane.as_deref(), state.config.context_lines).await;
+    let redacted_context = tmux_context
+        .as_ref()
+        .map(|value| state.redactor.redact(value, Some(&envelope.session_id)));
+    if envelope.hook_event_name == "SessionEnd" {
+        state.redactor.forget_session(&envelope.session_id);
+    }
+    let markdown = format_async_markdown(envelope, &redacted_payload, redacted_context.as_deref());
+
+    let message_id = if markdown.chars().count() <= state.transport.max_text_chars() {
+        state.transport.send_markdown(&markdown).await?
+    } else {
+        let file_name = format!(
+            "{}-{}-event.txt",
+            safe_filename(&envelope.session_name),
+            safe_filename(&envelope.hook_event_name.to_lowercase())
+        );
+        let mut text = format!(
+            "{} {} · {}\n\n{}",
+            icon_for(envelope),
+            envelope.hook_event_name,
+            envelope.session_name,
+            redacted_payload
+        );
+        if let Some(context) = redacted_context {
+            text.push_str("\n\nContext:\n");
+            text.push_str(&context);
+        }
+        state
+            .transport
+            .send_document(
+                &file_name,
+                text.into_bytes(),
+                Some(&format!(
+                    "*{}* · {}",
+                    md_escape_text(&event_title(envelope)),
+                    md_inline_code(&envelope.session_name)
+                )),
+            )
+            .await?
+    };
+    if envelope.hook_event_name == "Notification" {
+        db::insert_reply_route(
+            &state.db,
+            state.transport.chat_id(),
+            message_id,
+            envelope,
+            now_epoch(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Secret values from the session's dotenv files and the environment variables
+// the hook forwarded. Only the values are kept, in memory, for the redactor.
+// Forwarded variables were named in config, so all of them count; dotenv
+// entries only when they look like credentials.
+async fn load_session_secrets(
+    state: &DaemonState,
+    session_id: &str,
+    cwd: &str,
+    forwarded: BTreeMap<String, String>,
+) {
+    let mut secrets = Vec::new();
+    for file in &state.config.redaction.secret_files {
+        let path = Path::new(cwd).join(file);
+        match tokio::fs::read_to_string(&path).await {
+            Ok(text) => secrets.extend(
+                redact::parse_env_file(&text)
+                    .into_iter()
+                    .filter(|(name, value)| state.redactor.looks_secret(name, value)),
+            ),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                warn!(error = %err, path = %path.display(), "failed to read secret file");
+            }
+        }
+    }
+    secrets.extend(forwarded);
+    let tracked = state.redactor.set_session_secrets(session_id, secrets);
+    info!(session_id = %session_id, count = tracked, "loaded session secrets for redaction");
+}
+
+// Records the event on the session. Orphaning and SessionEnd drop a session's
+// secrets, so an event that brings it back to life reloads them; SessionStart
+// loads its own, forwarded values included.
+async fn touch_session(
+    state: &DaemonState,
+    envelope: &HookEnvelope,
+    status: Option<SessionStatus>,
+    now_epoch: i64,
+) -> Result<()> {
+    let revived = state.config.redaction.session_secrets
+        && envelope.hook_event_name != "SessionStart"
+        && status.is_some_and(SessionStatus::is_live)
+        && db::get_session(&state.db, &envelope.session_id)
+            .await?
+            .is_some_and(|session| !session.status.is_live());
+    db::upsert_session(&state.db, envelope, status, now_epoch).await?;
+    if revived {
+        load_session_secrets(state, &envelope.session_id, &envelope.cwd, BTreeMap::new()).await;
+    }
+    Ok(())
+}
+
+// Masks live only in memory, so a restarted daemon rereads the dotenv files of
+// sessions that are still running. Forwarded environment values came with
+// SessionStart and cannot be recovered until the session starts again.
+async fn reload_session_secrets(state: &DaemonState) {
+    if !state.config.redaction.session_secrets {
+        return;
+    }
+    let sessions = match db::list_sessions(&state.db, false).await {
+        Ok(sessions) => sessions,
+        Err(err) => {
+            warn!(error = %err, "failed to list sessions for secret reload");
+            return;
+        }
+    };
+    for session in sessions.iter().filter(|session| session.status.is_live()) {
+        load_session_secrets(state, &session.session_id, &session.cwd, BTreeMap::new()).await;
+    }
+}
+
+async fn receive_loop(state: DaemonState) -> Result<()> {
+    loop {
+        let inbound = tokio::select! {
+            _ = state.shutdown.cancelled() => return Ok(()),
+            inbound = state.transport.receive() => inbound?,
+        };
+        for event in inbound {
+            let result = match event {
+                Inbound::Callback(callback) => handle_callback_query(&state, callback).await,
+                Inbound::Command(command) => handle_command(&state, command).await,
+                Inbound::Message(message) => handle_message(&state, message).await,
+            };
+            if let Err(err) = result {
+                warn!(error = %err, transport = state.transport.name(), "failed processing inbound event");
             }
-            state.telegram.send_message(&out).await?;
         }
+    }
+}
+
+async fn handle_command(state: &DaemonState, command: InboundCommand) -> Result<()> {
+    if command.message.access != Access::Authorized {
         return Ok(());
     }
 
-    if text.starts_with("/switch") {
-        let mut parts = text.split_whitespace();
-        let _ = parts.next();
-        let Some(name) = parts.next() else {
-            let current = db::get_default_route(&state.db).await?;
-            let msg = match current {
-                Some(route) => format!("Current default session: {}", route.session_name),
-                None => "No default session set. Use /switch <name>.".to_string(),
+    match command.name.as_str() {
+        "peek" => handle_peek_command(state, &command.message).await?,
+        "diff" => handle_diff_command(state, &command.message).await?,
+        "log" => handle_log_command(state, &command.message).await?,
+        "grants" => handle_grants_command(state).await?,
+        "ontimeout" => handle_ontimeout_command(state, &command.args).await?,
+        "sessions" => {
+            let sessions = db::list_sessions(&state.db, false).await?;
+            let default = db::get_default_route(&state.db).await?;
+            if sessions.is_empty() {
+                state.transport.send_text("No active sessions.").await?;
+            } else {
+                let now = now_epoch();
+                let mut out = String::from("Sessions:\n");
+                for s in sessions {
+                    let marker = default
+                        .as_ref()
+                        .is_some_and(|route| route.session_id == s.session_id);
+                    let prefix = if marker { "* " } else { "- " };
+                    out.push_str(&format!(
+                        "{prefix}{} · {} ({})\n",
+                        s.name,
+                        session_summary(&s, now),
+                        s.session_id
+                    ));
+                }
+                state.transport.send_text(&out).await?;
+            }
+        }
+        "switch" => {
+            let Some(name) = command.args.split_whitespace().next() else {
+                let current = db::get_default_route(&state.db).await?;
+                let msg = match current {
+                    Some(route) => format!("Current default session: {}", route.session_name),
+                    None => "No default session set. Use /switch <name>.".to_string(),
+                };
+                state.transport.send_text(&msg).await?;
+                return Ok(());
+            };
+
+            let Some(route) = db::find_session_by_name(&state.db, name).await? else {
+                state
+                    .transport
+                    .send_text("Session not found. Use /sessions to list active sessions.")
+                    .await?;
+                return Ok(());
             };
-            state.telegram.send_message(&msg).await?;
-            return Ok(());
-        };
+            db::set_default_route(&state.db, &route, now_epoch()).await?;
+            state
+                .transport
+                .send_text(&format!(
+                    "Default session switched to {}.",
+                    route.session_name
+                ))
+                .await?;
+        }
+        _ => handle_message(state, command.message).await?,
+    }
+    Ok(())
+}
 
-        let Some(route) = db::find_session_by_name(&state.db, name).await? else {
+async fn handle_ontimeout_command(state: &DaemonState, args: &str) -> Result<()> {
+    let mut args = args.split_whitespace();
+    // `None` asks for the current setting, `Some(None)` clears the override.
+    let choice = match args.next() {
+        None => None,
+        Some("default") => Some(None),
+        Some("allow") => {
             state
-                .telegram
-                .send_message("Session not found. Use /sessions to list active sessions.")
+                .transport
+                .send_text("Auto-allow on timeout is only available on policy rules.")
                 .await?;
             return Ok(());
-        };
-        db::set_default_route(&state.db, &route, now_epoch()).await?;
+        }
+        Some(value) => match TimeoutOutcome::parse(value) {
+            Some(outcome) => Some(Some(outcome)),
+            None => {
+                state
+                    .transport
+                    .send_text("Usage: /ontimeout <deny|pause|default> [session]")
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+    let route = match args.next() {
+        Some(name) => db::find_session_by_name(&state.db, name).await?,
+        None => db::get_default_route(&state.db).await?,
+    };
+    let Some(route) = route else {
+        state
+            .transport
+            .send_text("Session not found. Use /ontimeout <deny|pause|default> <session>.")
+            .await?;
+        return Ok(());
+    };
+
+    let Some(outcome) = choice else {
+        let current = db::get_session_timeout_outcome(&state.db, &route.session_id)
+            .await?
+            .unwrap_or_else(|| format!("default ({})", state.config.on_timeout.as_str()));
         state
-            .telegram
-            .send_message(&format!(
-                "Default session switched to {}.",
+            .transport
+            .send_text(&format!(
+                "Timeout outcome for {}: {current}",
                 route.session_name
             ))
             .await?;
         return Ok(());
+    };
+    db::set_session_timeout_outcome(
+        &state.db,
+        &route.session_id,
+        outcome.map(TimeoutOutcome::as_str),
+    )
+    .await?;
+
+    // Prompts already on screen pick up the new outcome, except where a policy
+    // rule pinned one.
+    let effective = outcome.unwrap_or(state.config.on_timeout);
+    let refresh = {
+        let mut waiters = state.pending_waiters.lock().await;
+        waiters
+            .iter_mut()
+            .filter(|(_, waiter)| {
+                waiter.audit.session_id == route.session_id && waiter.rule_timeout.is_none()
+            })
+            .map(|(request_id, waiter)| {
+                waiter.prompt.on_timeout = effective;
+                request_id.clone()
+            })
+            .collect::<Vec<_>>()
+    };
+    for request_id in refresh {
+        refresh_countdown(state, &request_id).await;
+    }
+
+    state
+        .transport
+        .send_text(&format!(
+            "Timeout outcome for {} set to {}.",
+            route.session_name,
+            effective.as_str()
+        ))
+        .await?;
+    Ok(())
+}
+
+async fn handle_message(state: &DaemonState, message: InboundMessage) -> Result<()> {
+    if message.access != Access::Authorized {
+        // Route approvers need not be on the global user list, so a note
+        // reply from the default chat is left to the request's route to judge.
+        if message.access == Access::UnknownUser
+            && let Some(reply_to) = message.reply_to
+            && let Some(route) =
+                db::lookup_reply_route(&state.db, message.chat_id, reply_to).await?
+            && let Some(request_id) = route.request_id.as_deref()
+        {
+            return handle_permission_note(state, request_id, &message.text, &message).await;
+        }
+        return Ok(());
+    }
+    let text = message.text.clone();
+
+    let Some(reply_to) = message.reply_to else {
+        if let Some(route) = db::get_default_route(&state.db).await? {
+            if !session_is_live(state, &route.session_id).await? {
+                state
+                    .transport
+                    .send_text(&format!(
+                        "Default session {} has ended. Use /switch <name> to pick another.",
+                        route.session_name
+                    ))
+                    .await?;
+            } else if inject_reply(&route.tmux_pane, &text).await {
+                state
+                    .transport
+                    .send_text(&format!(
+                        "Sent message to default session {}.",
+                        route.session_name
+                    ))
+                    .await?;
+            } else {
+                state
+                    .transport
+                    .send_text("Failed to inject message into default session.")
+                    .await?;
+            }
+            return Ok(());
+        }
+        state
+            .transport
+            .send_text("Reply to a session message, or use /switch <name> first.")
+            .await?;
+        return Ok(());
+    };
+    let Some(route) = db::lookup_reply_route(&state.db, message.chat_id, reply_to).await? else {
+        return Ok(());
+    };
+    if let Some(request_id) = route.request_id.as_deref() {
+        return handle_permission_note(state, request_id, &text, &message).await;
+    }
+
+    if !session_is_live(state, &route.session_id).await? {
+        state
+            .transport
+            .send_text(&format!("Session {} has ended.", route.session_id))
+            .await?;
+    } else if inject_reply(&route.tmux_pane, &text).await {
+        state
+            .transport
+            .send_text(&format!("Sent reply to session {}.", route.session_id))
+            .await?;
+    } else {
+        state
+            .transport
+            .send_text("Failed to inject reply into tmux session.")
+            .await?;
+    }
+    Ok(())
+}
+
+async fn session_is_live(state: &DaemonState, session_id: &str) -> Result<bool> {
+    Ok(db::get_session(&state.db, session_id)
+        .await?
+        .is_some_and(|session| session.status.is_live()))
+}
+
+async fn request_permission_note(
+    state: &DaemonState,
+    request_id: &str,
+    message_id: Option<i64>,
+) -> Result<()> {
+    let live = state.pending_waiters.lock().await.contains_key(request_id);
+    if !live {
+        state
+            .transport
+            .send_text("This request is no longer waiting for a decision.")
+            .await?;
+        return Ok(());
+    }
+
+    let batched = match state.pending_waiters.lock().await.get_mut(request_id) {
+        Some(waiter) => {
+            waiter.awaiting_note = true;
+            waiter.batch.is_some()
+        }
+        None => false,
+    };
+    if let Some(message_id) = message_id {
+        // A digest holds several requests, so replies to it cannot be routed
+        // to this one; the force-reply prompt below still is.
+        if !batched {
+            db::insert_permission_note_route(
+                &state.db,
+                state.transport.chat_id(),
+                message_id,
+                request_id,
+                now_epoch(),
+            )
+            .await?;
+        }
+        edit_permission_message(
+            state,
+            request_id,
+            message_id,
+            "✍️ Waiting for your note — reply to deny with a reason.",
+        )
+        .await;
+    }
+    let prompt = format!(
+        "*✍️ Deny with note*\n\n{}",
+        md_escape_text(
+            "Reply with the reason Claude should see. The request still auto-denies if the timer runs out."
+        )
+    );
+    let prompt_id = state
+        .transport
+        .ask_reply(&prompt, "Why is this denied?")
+        .await?;
+    db::insert_permission_note_route(
+        &state.db,
+        state.transport.chat_id(),
+        prompt_id,
+        request_id,
+        now_epoch(),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn handle_permission_note(
+    state: &DaemonState,
+    request_id: &str,
+    note: &str,
+    message: &InboundMessage,
+) -> Result<()> {
+    let note = note.trim();
+    if note.is_empty() {
+        return Ok(());
     }
-
-    let Some(reply_to) = message.reply_to_message else {
-        if let Some(route) = db::get_default_route(&state.db).await? {
-            if inject_reply(&route.tmux_pane, &text).await {
-                state
-                    .telegram
-                    .send_message(&format!(
-                        "Sent message to default session {}.",
-                        route.session_name
-                    ))
-                    .await?;
-            } else {
-                state
-                    .telegram
-                    .send_message("Failed to inject message into default session.")
-                    .await?;
-            }
+    let actor = message.actor.as_ref();
+    let route = state
+        .pending_waiters
+        .lock()
+        .await
+        .get(request_id)
+        .and_then(|waiter| waiter.route.clone());
+    if let Some(route) = &route {
+        // A note is a deny, so it needs the same approver as the Deny button.
+        let (access, refusal) =
+            route_access(state, route, message.access, actor, message.chat_id, "deny");
+        if let Some(refusal) = refusal {
+            state.transport.send_text(&refusal).await?;
             return Ok(());
         }
+        if access != Access::Authorized {
+            warn!(
+                user_id = actor.map_or("", |actor| actor.id.as_str()),
+                request_id = %request_id,
+                "rejected permission note from outside the route"
+            );
+            return Ok(());
+        }
+    } else if message.access != Access::Authorized {
+        return Ok(());
+    }
+    let changed = db::transition_pending_state(&state.db, request_id, "denied").await?;
+    if !changed {
         state
-            .telegram
-            .send_message("Reply to a session message, or use /switch <name> first.")
+            .transport
+            .send_text("This request is no longer waiting for a decision.")
             .await?;
         return Ok(());
-    };
-    let Some(route) = db::lookup_reply_route(&state.db, reply_to.message_id).await? else {
-        return Ok(());
-    };
+    }
 
-    if inject_reply(&route.tmux_pane, &text).await {
-        state
-            .telegram
-            .send_message(&format!("Sent reply to session {}.", route.session_id))
-            .await?;
-    } else {
-        state
-            .telegram
-            .send_message("Failed to inject reply into tmux session.")
-            .await?;
+    let message_id = state
+        .pending_waiters
+        .lock()
+        .await
+        .get(request_id)
+        .map(|waiter| waiter.message_id);
+    if let Some(message_id) = message_id {
+        edit_permission_message(
+            state,
+            request_id,
+            message_id,
+            &match actor {
+                Some(actor) => format!("❌ Denied with note: {note}\nby {}", actor.name),
+                None => format!("❌ Denied with note: {note}"),
+            },
+        )
+        .await;
+    }
+    let hook_output = deny_permission_output(&format!("Denied by remote operator: {note}"));
+    complete_waiter(
+        state,
+        request_id,
+        hook_output,
+        Decider::Operator(actor.cloned()),
+    )
+    .await;
+    state
+        .transport
+        .send_text("Denied with note. Claude will see your reason.")
+        .await?;
+    Ok(())
+}
+
+async fn handle_grants_command(state: &DaemonState) -> Result<()> {
+    let grants = db::list_active_grants(&state.db).await?;
+    if grants.is_empty() {
+        state.transport.send_text("No learned grants.").await?;
+        return Ok(());
     }
+
+    let mut body = String::from("*🔓 Learned grants*\n");
+    let mut keyboard = Vec::with_capacity(grants.len());
+    for record in &grants {
+        let Some(grant) = GrantPattern::from_record(record) else {
+            continue;
+        };
+        let scope = if record.scope == "session" {
+            let name = db::get_session(&state.db, &record.scope_value)
+                .await?
+                .map(|session| session.name)
+                .unwrap_or_else(|| record.scope_value.clone());
+            format!("session {name}")
+        } else {
+            format!("repo {}", record.scope_value)
+        };
+        body.push_str(&format!(
+            "\n\\#{} · {} · {}",
+            record.id,
+            md_escape_text(&scope),
+            md_inline_code(&grant.describe())
+        ));
+        keyboard.push(vec![Button::new(
+            format!("Revoke #{}", record.id),
+            format!("grant:revoke:{}", record.id),
+        )]);
+    }
+    state.transport.ask(&body, &keyboard).await?;
     Ok(())
 }
 
-async fn handle_peek_command(state: &DaemonState, message: &TelegramMessage) -> Result<()> {
+async fn handle_peek_command(state: &DaemonState, message: &InboundMessage) -> Result<()> {
     let Some(session) = resolve_session_for_message(state, message).await? else {
         state
-            .telegram
-            .send_message("No active session. Use /sessions to pick one.")
+            .transport
+            .send_text("No active session. Use /sessions to pick one.")
             .await?;
         return Ok(());
     };
@@ -484,7 +2045,9 @@ async fn handle_peek_command(state: &DaemonState, message: &TelegramMessage) ->
     let recent_output = capture_context(Some(&session.tmux_pane), PEEK_CONTEXT_LINES)
         .await
         .unwrap_or_else(|| "No tmux output available".to_string());
-    let redacted_output = state.redactor.redact(&recent_output);
+    let redacted_output = state
+        .redactor
+        .redact(&recent_output, Some(&session.session_id));
     let running_command = detect_running_command(&session.tmux_pane)
         .await
         .unwrap_or_else(|| "idle".to_string());
@@ -503,10 +2066,10 @@ async fn handle_peek_command(state: &DaemonState, message: &TelegramMessage) ->
         md_inline_code(&current_file),
         md_code_block("", &preview_output)
     );
-    if body.chars().count() > MAX_TELEGRAM_TEXT {
+    if body.chars().count() > state.transport.max_text_chars() {
         preview_output = truncate_tail(&preview_output, 1800);
         body = format!(
-            "*🔵 Peek* · {}\n\n*Session* {}\n*Dir* {}\n*Task* {}\n*Running* {}\n*Current file* {}\n\n*Recent output*\n{}\n\nTruncated for Telegram",
+            "*🔵 Peek* · {}\n\n*Session* {}\n*Dir* {}\n*Task* {}\n*Running* {}\n*Current file* {}\n\n*Recent output*\n{}\n\nTruncated",
             md_inline_code(&session.name),
             md_inline_code(&session.session_id),
             md_inline_code(&session.cwd),
@@ -516,36 +2079,31 @@ async fn handle_peek_command(state: &DaemonState, message: &TelegramMessage) ->
             md_code_block("", &preview_output)
         );
     }
-    let keyboard = json!({
-        "inline_keyboard": [[
-            {"text":"Diff", "callback_data": format!("peek:diff:{}", session.session_id)},
-            {"text":"Log", "callback_data": format!("peek:log:{}", session.session_id)},
-            {"text":"Stop", "callback_data": format!("peek:stop:{}", session.session_id)}
-        ]]
-    });
-    state
-        .telegram
-        .send_markdown_with_markup(&body, Some(keyboard))
-        .await?;
+    let keyboard = vec![vec![
+        Button::new("Diff", format!("peek:diff:{}", session.session_id)),
+        Button::new("Log", format!("peek:log:{}", session.session_id)),
+        Button::new("Stop", format!("peek:stop:{}", session.session_id)),
+    ]];
+    state.transport.ask(&body, &keyboard).await?;
     Ok(())
 }
 
-async fn handle_diff_command(state: &DaemonState, message: &TelegramMessage) -> Result<()> {
+async fn handle_diff_command(state: &DaemonState, message: &InboundMessage) -> Result<()> {
     let Some(session) = resolve_session_for_message(state, message).await? else {
         state
-            .telegram
-            .send_message("No active session. Use /sessions to pick one.")
+            .transport
+            .send_text("No active session. Use /sessions to pick one.")
             .await?;
         return Ok(());
     };
     send_diff_for_session(state, &session).await
 }
 
-async fn handle_log_command(state: &DaemonState, message: &TelegramMessage) -> Result<()> {
+async fn handle_log_command(state: &DaemonState, message: &InboundMessage) -> Result<()> {
     let Some(session) = resolve_session_for_message(state, message).await? else {
         state
-            .telegram
-            .send_message("No active session. Use /sessions to pick one.")
+            .transport
+            .send_text("No active session. Use /sessions to pick one.")
             .await?;
         return Ok(());
     };
@@ -562,17 +2120,22 @@ async fn send_diff_for_session(state: &DaemonState, session: &db::SessionRecord)
         let msg = format!(
             "*❌ Diff failed* · {}\n\n{}",
             md_inline_code(&session.name),
-            md_code_block("", &state.redactor.redact(err.trim()))
+            md_code_block(
+                "",
+                &state.redactor.redact(err.trim(), Some(&session.session_id))
+            )
         );
-        state.telegram.send_markdown(&msg).await?;
+        state.transport.send_markdown(&msg).await?;
         return Ok(());
     }
 
     let diff_stdout = normalize_terminal_text(String::from_utf8_lossy(&output.stdout).as_ref());
-    let diff = state.redactor.redact(&diff_stdout);
+    let diff = state
+        .redactor
+        .redact(&diff_stdout, Some(&session.session_id));
     if diff.trim().is_empty() {
         state
-            .telegram
+            .transport
             .send_markdown(&format!(
                 "*✅ Diff* · {}\n\nNo changes",
                 md_inline_code(&session.name)
@@ -586,15 +2149,15 @@ async fn send_diff_for_session(state: &DaemonState, session: &db::SessionRecord)
         md_inline_code(&session.name),
         md_code_block("diff", &diff)
     );
-    if inline.chars().count() <= MAX_TELEGRAM_TEXT {
-        state.telegram.send_markdown(&inline).await?;
+    if inline.chars().count() <= state.transport.max_text_chars() {
+        state.transport.send_markdown(&inline).await?;
         return Ok(());
     }
 
     let filename = format!("{}-diff.patch", safe_filename(&session.name));
     let caption = format!("*🔵 Diff* · {}", md_inline_code(&session.name));
     state
-        .telegram
+        .transport
         .send_document(&filename, diff.into_bytes(), Some(&caption))
         .await?;
     Ok(())
@@ -604,79 +2167,229 @@ async fn send_log_for_session(state: &DaemonState, session: &db::SessionRecord)
     let log = capture_context(Some(&session.tmux_pane), LOG_LINES)
         .await
         .unwrap_or_else(|| "No tmux log available".to_string());
-    let redacted_log = state.redactor.redact(&log);
+    let redacted_log = state.redactor.redact(&log, Some(&session.session_id));
     let filename = format!("{}-log.txt", safe_filename(&session.name));
     let caption = format!("*🔵 Log* · {}", md_inline_code(&session.name));
     state
-        .telegram
+        .transport
         .send_document(&filename, redacted_log.into_bytes(), Some(&caption))
         .await?;
     Ok(())
 }
 
+// Only live sessions qualify: a reply to an ended session's message or a
+// default route to a dead pane falls through to the most recent live session.
 async fn resolve_session_for_message(
     state: &DaemonState,
-    message: &TelegramMessage,
-) -> Result<Option<db::SessionRecord>> {
-    if let Some(reply_to) = message.reply_to_message.as_ref()
-        && let Some(route) = db::lookup_reply_route(&state.db, reply_to.message_id).await?
+    message: &InboundMessage,
+) -> Result<Option<SessionRecord>> {
+    if let Some(reply_to) = message.reply_to
+        && let Some(route) = db::lookup_reply_route(&state.db, message.chat_id, reply_to).await?
         && let Some(session) = db::get_session(&state.db, &route.session_id).await?
+        && session.status.is_live()
     {
         return Ok(Some(session));
     }
 
     if let Some(default_route) = db::get_default_route(&state.db).await?
         && let Some(session) = db::get_session(&state.db, &default_route.session_id).await?
+        && session.status.is_live()
     {
         return Ok(Some(session));
     }
 
-    let sessions = db::list_sessions(&state.db).await?;
-    Ok(sessions.into_iter().next())
+    let sessions = db::list_sessions(&state.db, false).await?;
+    Ok(sessions
+        .into_iter()
+        .find(|session| session.status.is_live()))
 }
 
-async fn handle_callback_query(state: &DaemonState, callback: TelegramCallbackQuery) -> Result<()> {
-    state.telegram.answer_callback_query(&callback.id).await?;
-    if callback.message.as_ref().map(|m| m.chat.id) != Some(state.telegram.chat_id) {
-        return Ok(());
+pub fn session_summary(session: &SessionRecord, now: i64) -> String {
+    match (session.status.is_live(), session.ended_at) {
+        (false, Some(ended_at)) => format!(
+            "{} {} ago",
+            session.status.as_str(),
+            format_age(now - ended_at)
+        ),
+        _ => format!(
+            "{} · up {} · seen {} ago",
+            session.status.as_str(),
+            format_age(now - session.started_at),
+            format_age(now - session.last_seen_at)
+        ),
     }
+}
 
-    let Some(data) = callback.data.as_deref() else {
-        return Ok(());
+fn format_age(seconds: i64) -> String {
+    match seconds.max(0) {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
+async fn handle_callback_query(state: &DaemonState, callback: InboundCallback) -> Result<()> {
+    let stale = if let Some(rest) = callback.data.strip_prefix("permit:") {
+        let request_id = rest.split(':').next().unwrap_or_default();
+        !state.pending_waiters.lock().await.contains_key(request_id)
+    } else if let Some(rest) = callback.data.strip_prefix("batch:") {
+        let batch_id = rest.split(':').next().unwrap_or_default();
+        !state.batches.lock().await.contains_key(batch_id)
+    } else {
+        false
     };
-    let mut parts = data.splitn(3, ':');
+    let route = match callback.data.strip_prefix("permit:") {
+        Some(rest) if !stale => {
+            let request_id = rest.split(':').next().unwrap_or_default();
+            state
+                .pending_waiters
+                .lock()
+                .await
+                .get(request_id)
+                .and_then(|waiter| waiter.route.clone())
+        }
+        _ => None,
+    };
+    let (access, refusal) = match &route {
+        Some(route) => route_access(
+            state,
+            route,
+            callback.access,
+            callback.actor.as_ref(),
+            callback.chat_id,
+            callback.data.rsplit(':').next().unwrap_or_default(),
+        ),
+        None => (callback.access, None),
+    };
+    let notice = match access {
+        Access::UnknownUser => Some(
+            "You are not authorized to answer codelatch prompts. Ask the owner to add your user ID."
+                .to_string(),
+        ),
+        _ => refusal
+            .clone()
+            .or_else(|| stale.then(|| "This request is no longer live.".to_string())),
+    };
+    state
+        .transport
+        .answer_callback(&callback.id, notice.as_deref())
+        .await?;
+    if refusal.is_some() {
+        return Ok(());
+    }
+    if access != Access::Authorized {
+        if access == Access::UnknownUser {
+            let actor = callback.actor.as_ref();
+            warn!(
+                user_id = actor.map_or("", |actor| actor.id.as_str()),
+                user = actor.map_or("", |actor| actor.name.as_str()),
+                "rejected callback from unauthorized user"
+            );
+        }
+        return Ok(());
+    }
+    let actor = callback.actor.as_ref();
+
+    let mut parts = callback.data.splitn(3, ':');
     let kind = parts.next().unwrap_or_default();
     match kind {
         "permit" => {
             let request_id = parts.next().unwrap_or_default();
             let action = parts.next().unwrap_or_default();
-            if request_id.is_empty() {
+            if request_id.is_empty() || stale {
                 return Ok(());
             }
 
-            let (next_state, status_text, hook_output) = match action {
-                "allow" => ("approved", "✅ Approved", allow_permission_output()),
+            let (next_state, status_text, hook_output, grant_scope) = match action {
+                "allow" => ("approved", "✅ Approved", allow_permission_output(), None),
+                "allow_session" => (
+                    "approved",
+                    "✅ Approved",
+                    allow_permission_output(),
+                    Some("session"),
+                ),
+                "allow_repo" => (
+                    "approved",
+                    "✅ Approved",
+                    allow_permission_output(),
+                    Some("repo"),
+                ),
                 "deny" => (
                     "denied",
                     "❌ Denied",
                     deny_permission_output("Denied by remote operator"),
+                    None,
                 ),
+                "deny_note" => {
+                    return request_permission_note(state, request_id, callback.message_id).await;
+                }
+                "extend" => return extend_permission(state, request_id).await,
                 _ => return Ok(()),
             };
 
+            let actor = if next_state == "approved" {
+                match record_approval(state, request_id, actor).await? {
+                    Approval::Complete(actor) => actor,
+                    Approval::Pending => return Ok(()),
+                }
+            } else {
+                actor.cloned()
+            };
             let changed = db::transition_pending_state(&state.db, request_id, next_state).await?;
             if changed {
-                if let Some(message) = callback.message {
-                    let _ = state
-                        .telegram
-                        .edit_message(
-                            message.message_id,
-                            &format!("🔴 Permission\n\n{status_text}"),
-                        )
-                        .await;
+                let mut status_text = signed(status_text, actor.as_ref());
+                // The decision is already made; a grant that fails to save
+                // must not keep the hook waiting until it times out.
+                if let Some(scope) = grant_scope {
+                    match persist_grant(state, request_id, scope).await {
+                        Ok(Some(grant)) => {
+                            let scope_label = if scope == "session" {
+                                "for this session"
+                            } else {
+                                "in this repo"
+                            };
+                            status_text.push_str(&format!(
+                                "\nAlways allowed {scope_label}: {}",
+                                grant.describe()
+                            ));
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            warn!(error = %err, request_id = %request_id, "failed to store learned grant");
+                            status_text.push_str("\n⚠️ The grant could not be saved.");
+                        }
+                    }
                 }
-                complete_waiter(state, request_id, hook_output).await;
+                if let Some(message_id) = callback.message_id {
+                    edit_permission_message(state, request_id, message_id, &status_text).await;
+                }
+                complete_waiter(state, request_id, hook_output, Decider::Operator(actor)).await;
+            }
+        }
+        "batch" => {
+            let batch_id = parts.next().unwrap_or_default();
+            let action = parts.next().unwrap_or_default();
+            if batch_id.is_empty() || stale {
+                return Ok(());
+            }
+            resolve_batch(state, batch_id, action, actor).await?;
+        }
+        "grant" => {
+            let action = parts.next().unwrap_or_default();
+            let grant_id = parts.next().unwrap_or_default();
+            if action != "revoke" {
+                return Ok(());
             }
+            let Ok(grant_id) = grant_id.parse::<i64>() else {
+                return Ok(());
+            };
+            let text = if db::revoke_grant(&state.db, grant_id, now_epoch()).await? {
+                format!("Revoked grant #{grant_id}.")
+            } else {
+                format!("Grant #{grant_id} is already revoked.")
+            };
+            state.transport.send_text(&text).await?;
         }
         "peek" => {
             let action = parts.next().unwrap_or_default();
@@ -691,6 +2404,139 @@ async fn handle_callback_query(state: &DaemonState, callback: TelegramCallbackQu
     Ok(())
 }
 
+// Route chats and approvers are not authorized globally: they may answer the
+// requests routed to them and nothing else. The refusal explains an answer the
+// route turns down, either from someone off its approver list or one that
+// would store a grant the request class must never skip ahead with.
+// Callbacks and Deny + note replies both answer a routed request, so both are
+// checked against the route's chat and approvers rather than the global lists.
+fn route_access(
+    state: &DaemonState,
+    route: &Route,
+    access: Access,
+    actor: Option<&Actor>,
+    chat_id: Option<i64>,
+    action: &str,
+) -> (Access, Option<String>) {
+    let in_chat = match route.chat_id {
+        Some(route_chat) => chat_id == Some(route_chat),
+        None => access != Access::UnknownChat,
+    };
+    if !in_chat {
+        return (Access::UnknownChat, None);
+    }
+    let actor_id = actor.map(|actor| actor.id.as_str());
+    let access = if !route.approver_ids.is_empty() {
+        if !actor_id.is_some_and(|id| route.allows(id)) {
+            return (
+                Access::Authorized,
+                Some(format!(
+                    "Only approvers for the {} route can answer this request.",
+                    route.name
+                )),
+            );
+        }
+        Access::Authorized
+    } else if route.chat_id.is_some() {
+        // An empty user list still means "anyone in the chat".
+        let users = &state.config.telegram_authorized_user_ids;
+        if users.is_empty()
+            || actor_id.is_some_and(|id| users.iter().any(|user| user.to_string() == id))
+        {
+            Access::Authorized
+        } else {
+            Access::UnknownUser
+        }
+    } else {
+        access
+    };
+    let refusal = (access == Access::Authorized
+        && matches!(action, "allow_session" | "allow_repo"))
+    .then(|| {
+        format!(
+            "Requests on the {} route can only be allowed once.",
+            route.name
+        )
+    });
+    (access, refusal)
+}
+
+enum Approval {
+    Complete(Option<Actor>),
+    Pending,
+}
+
+// Routes that need several approvals collect distinct approvers on the waiter;
+// only the approval that reaches the quorum becomes the decision, signed by
+// everyone who approved.
+async fn record_approval(
+    state: &DaemonState,
+    request_id: &str,
+    actor: Option<&Actor>,
+) -> Result<Approval> {
+    let (progress, transport) = {
+        let mut waiters = state.pending_waiters.lock().await;
+        let Some(waiter) = waiters.get_mut(request_id) else {
+            return Ok(Approval::Complete(actor.cloned()));
+        };
+        let required = waiter
+            .route
+            .as_ref()
+            .map_or(1, |route| route.required_approvals);
+        if required <= 1 {
+            return Ok(Approval::Complete(actor.cloned()));
+        }
+        let transport = waiter.transport.clone();
+        let progress = match actor {
+            None => Err("This request needs approvals from identified users."),
+            Some(actor) if waiter.approvals.iter().any(|seen| seen.id == actor.id) => {
+                Err("You already approved this request. It needs another approver.")
+            }
+            Some(actor) => {
+                waiter.approvals.push(actor.clone());
+                if waiter.approvals.len() >= required {
+                    return Ok(Approval::Complete(Some(Actor {
+                        id: join_actors(&waiter.approvals, |actor| &actor.id),
+                        name: join_actors(&waiter.approvals, |actor| &actor.name),
+                    })));
+                }
+                waiter.prompt.approved_by = waiter
+                    .approvals
+                    .iter()
+                    .map(|actor| actor.name.clone())
+                    .collect();
+                Ok(json!(
+                    waiter
+                        .approvals
+                        .iter()
+                        .map(|actor| json!({ "id": actor.id, "name": actor.name }))
+                        .collect::<Vec<_>>()
+                )
+                .to_string())
+            }
+        };
+        (progress, transport)
+    };
+    match progress {
+        Ok(approvals) => {
+            db::set_pending_approvals(&state.db, request_id, &approvals).await?;
+            refresh_countdown(state, request_id).await;
+        }
+        Err(text) => {
+            transport.send_text(text).await?;
+        }
+    }
+    Ok(Approval::Pending)
+}
+
+fn join_actors(actors: &[Actor], field: impl Fn(&Actor) -> &String) -> String {
+    actors
+        .iter()
+        .map(|actor| field(actor).as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 async fn handle_peek_callback_action(
     state: &DaemonState,
     action: &str,
@@ -698,8 +2544,8 @@ async fn handle_peek_callback_action(
 ) -> Result<()> {
     let Some(session) = db::get_session(&state.db, session_id).await? else {
         state
-            .telegram
-            .send_message("Session is no longer active.")
+            .transport
+            .send_text("Session is no longer active.")
             .await?;
         return Ok(());
     };
@@ -714,11 +2560,11 @@ async fn handle_peek_callback_action(
                     md_inline_code(&session.name),
                     md_inline_code(&session.tmux_pane)
                 );
-                state.telegram.send_markdown(&text).await?;
+                state.transport.send_markdown(&text).await?;
             } else {
                 state
-                    .telegram
-                    .send_message("Failed to send interrupt to tmux pane.")
+                    .transport
+                    .send_text("Failed to send interrupt to tmux pane.")
                     .await?;
             }
         }
@@ -728,10 +2574,16 @@ async fn handle_peek_callback_action(
     Ok(())
 }
 
-async fn complete_waiter(state: &DaemonState, request_id: &str, hook_output: Value) {
-    let mut waiters = state.pending_waiters.lock().await;
-    if let Some(sender) = waiters.remove(request_id) {
-        let _ = sender.send(HookResponseEnvelope {
+async fn complete_waiter(
+    state: &DaemonState,
+    request_id: &str,
+    hook_output: Value,
+    decider: Decider,
+) {
+    let waiter = state.pending_waiters.lock().await.remove(request_id);
+    if let Some(waiter) = waiter {
+        record_audit(state, &waiter.audit, &hook_output, &decider).await;
+        let _ = waiter.sender.send(HookResponseEnvelope {
             request_id: request_id.to_string(),
             hook_output,
         });
@@ -755,8 +2607,12 @@ async fn capture_context(tmux_pane: Option<&str>, lines: usize) -> Option<String
 }
 
 async fn send_interrupt(tmux_pane: &str) -> bool {
+    send_keys(tmux_pane, "C-c").await
+}
+
+async fn send_keys(tmux_pane: &str, key: &str) -> bool {
     Command::new("tmux")
-        .args(["send-keys", "-t", tmux_pane, "C-c"])
+        .args(["send-keys", "-t", tmux_pane, key])
         .status()
         .await
         .is_ok_and(|status| status.success())
@@ -900,6 +2756,15 @@ fn latest_nonempty_line(input: &str) -> Option<String> {
     None
 }
 
+fn truncate_head(input: &str, max_chars: usize) -> String {
+    if input.chars().count() <= max_chars {
+        return input.to_string();
+    }
+    let mut out = input.chars().take(max_chars).collect::<String>();
+    out.push_str("\n…");
+    out
+}
+
 fn truncate_tail(input: &str, max_chars: usize) -> String {
     let chars: Vec<char> = input.chars().collect();
     if chars.len() <= max_chars {
@@ -933,16 +2798,6 @@ fn now_epoch() -> i64 {
         .as_secs() as i64
 }
 
-fn extract_command(envelope: &HookEnvelope) -> String {
-    envelope
-        .payload
-        .get("tool_input")
-        .and_then(|tool| tool.get("command"))
-        .and_then(Value::as_str)
-        .unwrap_or("<unknown command>")
-        .to_string()
-}
-
 fn icon_for(envelope: &HookEnvelope) -> &'static str {
     if envelope.hook_event_name == "Notification" {
         let notification_type = envelope
@@ -986,6 +2841,24 @@ fn deny_permission_output(message: &str) -> Value {
     })
 }
 
+fn pre_tool_use_output(permission: &Value) -> Value {
+    let decision = permission.pointer("/hookSpecificOutput/decision");
+    let behavior = decision
+        .and_then(|decision| decision.get("behavior"))
+        .and_then(Value::as_str)
+        .unwrap_or("deny");
+    let mut output = json!({
+      "hookSpecificOutput": {
+        "hookEventName": "PreToolUse",
+        "permissionDecision": behavior
+      }
+    });
+    if let Some(message) = decision.and_then(|decision| decision.get("message")) {
+        output["hookSpecificOutput"]["permissionDecisionReason"] = message.clone();
+    }
+    output
+}
+
 fn format_async_markdown(
     envelope: &HookEnvelope,
     redacted_payload: &str,
@@ -1121,435 +2994,47 @@ fn normalize_terminal_text(input: &str) -> String {
         .collect()
 }
 
-impl TelegramClient {
-    async fn send_message(&self, text: &str) -> Result<i64> {
-        let token = self.token.expose_secret();
-        let url = format!("{TELEGRAM_API}/bot{token}/sendMessage");
-        let payload = json!({ "chat_id": self.chat_id, "text": text });
-        let client = self.http.clone();
-
-        self.with_retry(|| {
-            let payload = payload.clone();
-            let url = url.clone();
-            let client = client.clone();
-            async move {
-                let response: TelegramResponse<TelegramSentMessage> = client
-                    .post(&url)
-                    .json(&payload)
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
-                if !response.ok {
-                    return Err(AppError::TelegramApi(
-                        response
-                            .description
-                            .unwrap_or_else(|| "sendMessage failed".to_string()),
-                    ));
-                }
-                let Some(message) = response.result else {
-                    return Err(AppError::TelegramApi(
-                        "sendMessage missing result".to_string(),
-                    ));
-                };
-                Ok(message.message_id)
-            }
-        })
-        .await
-    }
-
-    async fn send_markdown(&self, text: &str) -> Result<i64> {
-        self.send_markdown_with_markup(text, None).await
-    }
-
-    async fn send_markdown_with_markup(
-        &self,
-        text: &str,
-        reply_markup: Option<Value>,
-    ) -> Result<i64> {
-        let token = self.token.expose_secret();
-        let url = format!("{TELEGRAM_API}/bot{token}/sendMessage");
-        let mut payload = json!({
-            "chat_id": self.chat_id,
-            "text": text,
-            "parse_mode": "MarkdownV2"
-        });
-        if let Some(markup) = reply_markup {
-            payload["reply_markup"] = markup;
-        }
-        let client = self.http.clone();
-
-        self.with_retry(|| {
-            let payload = payload.clone();
-            let url = url.clone();
-            let client = client.clone();
-            async move {
-                let response: TelegramResponse<TelegramSentMessage> = client
-                    .post(&url)
-                    .json(&payload)
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
-                if !response.ok {
-                    return Err(AppError::TelegramApi(
-                        response
-                            .description
-                            .unwrap_or_else(|| "sendMessage markdown failed".to_string()),
-                    ));
-                }
-                let Some(message) = response.result else {
-                    return Err(AppError::TelegramApi(
-                        "sendMessage markdown missing result".to_string(),
-                    ));
-                };
-                Ok(message.message_id)
-            }
-        })
-        .await
-    }
-
-    async fn send_document(
-        &self,
-        file_name: &str,
-        bytes: Vec<u8>,
-        caption: Option<&str>,
-    ) -> Result<i64> {
-        let token = self.token.expose_secret();
-        let url = format!("{TELEGRAM_API}/bot{token}/sendDocument");
-        let file_name = file_name.to_string();
-        let caption = caption.map(str::to_string);
-        let chat_id = self.chat_id.to_string();
-        let document = bytes;
-        let client = self.http.clone();
-
-        self.with_retry(|| {
-            let url = url.clone();
-            let file_name = file_name.clone();
-            let caption = caption.clone();
-            let chat_id = chat_id.clone();
-            let data = document.clone();
-            let client = client.clone();
-            async move {
-                let part = multipart::Part::bytes(data)
-                    .file_name(file_name)
-                    .mime_str("text/plain; charset=utf-8")
-                    .map_err(|err| AppError::TelegramApi(err.to_string()))?;
-                let mut form = multipart::Form::new()
-                    .text("chat_id", chat_id)
-                    .part("document", part);
-                if let Some(caption) = caption {
-                    form = form
-                        .text("caption", caption)
-                        .text("parse_mode", "MarkdownV2".to_string());
-                }
-
-                let response: TelegramResponse<TelegramSentMessage> = client
-                    .post(&url)
-                    .multipart(form)
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
-                if !response.ok {
-                    return Err(AppError::TelegramApi(
-                        response
-                            .description
-                            .unwrap_or_else(|| "sendDocument failed".to_string()),
-                    ));
-                }
-                let Some(message) = response.result else {
-                    return Err(AppError::TelegramApi(
-                        "sendDocument missing result".to_string(),
-                    ));
-                };
-                Ok(message.message_id)
-            }
-        })
-        .await
-    }
-
-    async fn send_permission_message(
-        &self,
-        session_name: &str,
-        command: &str,
-        cwd: &str,
-        request_id: &str,
-        timeout_seconds: u64,
-    ) -> Result<i64> {
-        let minutes = timeout_seconds / 60;
-        let seconds = timeout_seconds % 60;
-        let text = format!(
-            "*🔴 Permission* · {}\n\n*Claude wants to run*\n{}\n\n*Dir* {}\n\nAuto deny in {:02}:{:02}",
-            md_inline_code(session_name),
-            md_code_block("bash", command),
-            md_inline_code(cwd),
-            minutes,
-            seconds
-        );
-
-        let reply_markup = json!({
-          "inline_keyboard": [[
-            {"text":"Allow", "callback_data": format!("permit:{request_id}:allow")},
-            {"text":"Deny", "callback_data": format!("permit:{request_id}:deny")}
-          ]]
-        });
-
-        self.send_markdown_with_markup(&text, Some(reply_markup))
-            .await
-    }
-
-    async fn edit_message(&self, message_id: i64, text: &str) -> Result<()> {
-        let token = self.token.expose_secret();
-        let url = format!("{TELEGRAM_API}/bot{token}/editMessageText");
-        let payload = json!({
-            "chat_id": self.chat_id,
-            "message_id": message_id,
-            "text": text
-        });
-        let client = self.http.clone();
-
-        self.with_retry(|| {
-            let payload = payload.clone();
-            let url = url.clone();
-            let client = client.clone();
-            async move {
-                let response: TelegramResponse<Value> = client
-                    .post(&url)
-                    .json(&payload)
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
-                if !response.ok {
-                    return Err(AppError::TelegramApi(
-                        response
-                            .description
-                            .unwrap_or_else(|| "editMessageText failed".to_string()),
-                    ));
-                }
-                Ok(())
-            }
-        })
-        .await
-    }
-
-    async fn answer_callback_query(&self, callback_query_id: &str) -> Result<()> {
-        let token = self.token.expose_secret();
-        let url = format!("{TELEGRAM_API}/bot{token}/answerCallbackQuery");
-        let payload = json!({ "callback_query_id": callback_query_id });
-        let client = self.http.clone();
-
-        self.with_retry(|| {
-            let payload = payload.clone();
-            let url = url.clone();
-            let client = client.clone();
-            async move {
-                let response: TelegramResponse<Value> = client
-                    .post(&url)
-                    .json(&payload)
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
-                if !response.ok {
-                    return Err(AppError::TelegramApi(
-                        response
-                            .description
-                            .unwrap_or_else(|| "answerCallbackQuery failed".to_string()),
-                    ));
-                }
-                Ok(())
-            }
-        })
-        .await
-    }
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_age, format_duration, normalize_terminal_text, reminders_crossed, session_summary,
+    };
+    use crate::db::{SessionRecord, SessionStatus};
 
-    async fn get_updates(&self, offset: i64) -> Result<Vec<TelegramUpdate>> {
-        let token = self.token.expose_secret();
-        let url = format!("{TELEGRAM_API}/bot{token}/getUpdates");
-        let payload = json!({
-            "timeout": 20,
-            "offset": offset,
-            "allowed_updates": ["message", "callback_query"]
-        });
-        let client = self.http.clone();
-
-        self.with_retry(|| {
-            let payload = payload.clone();
-            let url = url.clone();
-            let client = client.clone();
-            async move {
-                let response: TelegramResponse<Vec<TelegramUpdate>> = client
-                    .post(&url)
-                    .json(&payload)
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
-                if !response.ok {
-                    return Err(AppError::TelegramApi(
-                        response
-                            .description
-                            .unwrap_or_else(|| "getUpdates failed".to_string()),
-                    ));
-                }
-                Ok(response.result.unwrap_or_default())
-            }
-        })
-        .await
+    #[test]
+    fn reminders_cross_at_configured_fractions_of_the_window() {
+        let percents = [50, 90];
+        assert_eq!(reminders_crossed(&percents, 100, 700, 399), 0);
+        assert_eq!(reminders_crossed(&percents, 100, 700, 400), 1);
+        assert_eq!(reminders_crossed(&percents, 100, 700, 640), 2);
+        assert_eq!(reminders_crossed(&[], 100, 700, 700), 0);
+        assert_eq!(format_duration(600), "10m");
+        assert_eq!(format_duration(3600), "1h");
+        assert_eq!(format_duration(90), "90s");
     }
 
-    async fn with_retry<T, F, Fut>(&self, mut op: F) -> Result<T>
-    where
-        F: FnMut() -> Fut,
-        Fut: Future<Output = Result<T>>,
-    {
-        let mut backoff = ExponentialBackoff {
-            initial_interval: Duration::from_millis(250),
-            max_interval: Duration::from_secs(4),
-            max_elapsed_time: Some(Duration::from_secs(20)),
-            ..ExponentialBackoff::default()
+    #[test]
+    fn session_summaries_show_status_and_age() {
+        let mut session = SessionRecord {
+            session_id: "s1".to_string(),
+            name: "api".to_string(),
+            cwd: "/repo".to_string(),
+            tmux_pane: "%1".to_string(),
+            status: SessionStatus::Idle,
+            started_at: 1_000,
+            last_seen_at: 8_000,
+            ended_at: None,
         };
-        backoff.reset();
-
-        let mut attempts: u32 = 0;
-        loop {
-            self.limiter.until_ready().await;
-            match op().await {
-                Ok(value) => return Ok(value),
-                Err(err) => {
-                    attempts += 1;
-                    if !is_retryable_telegram_error(&err) {
-                        return Err(err);
-                    }
-                    let Some(delay) = backoff.next_backoff() else {
-                        return Err(err);
-                    };
-                    warn!(
-                        attempt = attempts,
-                        delay_ms = delay.as_millis() as u64,
-                        error = %err,
-                        "retrying telegram request"
-                    );
-                    sleep(delay).await;
-                }
-            }
-        }
-    }
-}
-
-fn is_retryable_telegram_error(err: &AppError) -> bool {
-    match err {
-        AppError::Http(http) => {
-            http.is_timeout()
-                || http.is_connect()
-                || http.is_request()
-                || http
-                    .status()
-                    .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
-        }
-        AppError::TelegramApi(message) => {
-            let lower = message.to_ascii_lowercase();
-            lower.contains("too many requests")
-                || lower.contains("retry after")
-                || lower.contains("timed out")
-                || lower.contains("bad gateway")
-                || lower.contains("gateway timeout")
-                || lower.contains("internal server error")
-        }
-        _ => false,
-    }
-}
-
-struct Redactor {
-    patterns: Vec<Regex>,
-}
-
-impl Redactor {
-    fn new() -> Result<Self> {
-        let patterns = vec![
-            Regex::new(r"(?i)bearer\s+[A-Za-z0-9\-._~+/]+=*")
-                .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-            Regex::new(r"gh[pousr]_[A-Za-z0-9]{20,}")
-                .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-            Regex::new(r"sk-[A-Za-z0-9]{20,}")
-                .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-            Regex::new(r"AKIA[0-9A-Z]{16}")
-                .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-            Regex::new(r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9._-]+\.[A-Za-z0-9._-]+")
-                .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-            Regex::new(
-                r"(?s)-----BEGIN [A-Z ]+PRIVATE KEY-----.*?-----END [A-Z ]+PRIVATE KEY-----",
-            )
-            .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-            Regex::new(r"(?im)^\s*[A-Z0-9_]*(TOKEN|SECRET|PASSWORD|API_KEY)[A-Z0-9_]*\s*=\s*.+$")
-                .map_err(|err| AppError::TelegramApi(err.to_string()))?,
-        ];
-        Ok(Self { patterns })
-    }
-
-    fn redact(&self, input: &str) -> String {
-        let mut out = input.to_string();
-        for pattern in &self.patterns {
-            out = pattern.replace_all(&out, "[REDACTED]").to_string();
-        }
-        out
+        assert_eq!(
+            session_summary(&session, 8_200),
+            "idle · up 2h · seen 3m ago"
+        );
+        session.status = SessionStatus::Orphaned;
+        session.ended_at = Some(8_190);
+        assert_eq!(session_summary(&session, 8_200), "orphaned 10s ago");
+        assert_eq!(format_age(3 * 86_400), "3d");
+        assert_eq!(format_age(-5), "0s");
     }
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramResponse<T> {
-    ok: bool,
-    result: Option<T>,
-    description: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct BotUser {
-    username: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramSentMessage {
-    message_id: i64,
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramUpdate {
-    update_id: i64,
-    message: Option<TelegramMessage>,
-    callback_query: Option<TelegramCallbackQuery>,
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramMessage {
-    message_id: i64,
-    chat: TelegramChat,
-    text: Option<String>,
-    reply_to_message: Option<TelegramReplyMessage>,
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramReplyMessage {
-    message_id: i64,
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramCallbackQuery {
-    id: String,
-    data: Option<String>,
-    message: Option<TelegramMessage>,
-}
-
-#[derive(Debug, Deserialize)]
-struct TelegramChat {
-    id: i64,
-}
-
-#[cfg(test)]
-mod tests {
-    use super::normalize_terminal_text;
 
     #[test]
     fn strips_color_ansi_sequences() {
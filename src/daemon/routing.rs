@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use regex::Regex;
+
+use super::{
+    gate::{DenyList, WRITE_TOOLS, normalize},
+    policy::{PermissionSubject, glob_regex},
+};
+use crate::{
+    config::{RequestClass, RouteRule},
+    errors::{AppError, Result},
+};
+
+const NETWORK_COMMANDS: &[&str] = &[
+    r"(^|[;&|]\s*|\s)(curl|wget|ssh|scp|sftp|rsync|nc|ncat|telnet|ftp)\s",
+    r"\bgit\s+(clone|fetch|pull|push)\b",
+];
+const NETWORK_TOOLS: &[&str] = &["WebFetch", "WebSearch"];
+
+pub struct Router {
+    routes: Vec<CompiledRoute>,
+    destructive: DenyList,
+    network: Vec<Regex>,
+}
+
+struct CompiledRoute {
+    class: RequestClass,
+    mcp_server: Option<Regex>,
+    route: Route,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub name: String,
+    pub chat_id: Option<i64>,
+    pub approver_ids: Vec<i64>,
+    pub required_approvals: usize,
+}
+
+impl Route {
+    pub fn allows(&self, actor_id: &str) -> bool {
+        self.approver_ids.is_empty()
+            || self
+                .approver_ids
+                .iter()
+                .any(|id| id.to_string() == actor_id)
+    }
+}
+
+impl Router {
+    pub fn new(rules: &[RouteRule], destructive: DenyList) -> Result<Self> {
+        let mut routes = Vec::with_capacity(rules.len());
+        for (index, rule) in rules.iter().enumerate() {
+            let name = rule.label(index);
+            let mcp_server = rule
+                .mcp_server
+                .as_deref()
+                .map(|pattern| {
+                    glob_regex(pattern)
+                        .map_err(|err| AppError::ConfigInvalid(format!("route {name}: {err}")))
+                })
+                .transpose()?;
+            routes.push(CompiledRoute {
+                class: rule.class,
+                mcp_server,
+                route: Route {
+                    name,
+                    chat_id: rule.chat_id,
+                    approver_ids: rule.approver_ids.clone(),
+                    required_approvals: rule.required_approvals,
+                },
+            });
+        }
+        let network = NETWORK_COMMANDS
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|err| AppError::ConfigInvalid(err.to_string()))?;
+        Ok(Self {
+            routes,
+            destructive,
+            network,
+        })
+    }
+
+    pub fn route(&self, subject: &PermissionSubject<'_>) -> Option<Route> {
+        self.routes
+            .iter()
+            .find(|compiled| self.matches(compiled, subject))
+            .map(|compiled| compiled.route.clone())
+    }
+
+    fn matches(&self, compiled: &CompiledRoute, subject: &PermissionSubject<'_>) -> bool {
+        let value = subject.subject.map(str::trim).unwrap_or_default();
+        let bash = subject.tool_name == "Bash";
+        match compiled.class {
+            RequestClass::Destructive => bash && self.destructive.matching(value).is_some(),
+            RequestClass::Network => {
+                NETWORK_TOOLS.contains(&subject.tool_name)
+                    || (bash && self.network.iter().any(|regex| regex.is_match(value)))
+            }
+            RequestClass::OutsideCwd => {
+                WRITE_TOOLS.contains(&subject.tool_name)
+                    && !value.is_empty()
+                    && !normalize(&Path::new(subject.cwd).join(value))
+                        .starts_with(normalize(Path::new(subject.cwd)))
+            }
+            RequestClass::Mcp => {
+                let mut parts = subject.tool_name.splitn(3, "__");
+                let (Some("mcp"), Some(server)) = (parts.next(), parts.next()) else {
+                    return false;
+                };
+                compiled
+                    .mcp_server
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.is_match(server))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Router;
+    use crate::{
+        config::{GateConfig, RequestClass, RouteRule},
+        daemon::{gate::DenyList, policy::PermissionSubject},
+    };
+
+    fn rule(name: &str, class: RequestClass) -> RouteRule {
+        RouteRule {
+            name: Some(name.to_string()),
+            class,
+            mcp_server: None,
+            chat_id: None,
+            approver_ids: Vec::new(),
+            required_approvals: 1,
+        }
+    }
+
+    fn route_name(router: &Router, tool_name: &str, value: &str) -> Option<String> {
+        router
+            .route(&PermissionSubject {
+                tool_name,
+                subject: Some(value),
+                cwd: "/work/repo",
+            })
+            .map(|route| route.name)
+    }
+
+    #[test]
+    fn requests_are_classified_in_rule_order() {
+        let destructive =
+            DenyList::new(&GateConfig::default().deny_commands).expect("deny-list compiles");
+        let router = Router::new(
+            &[
+                RouteRule {
+                    mcp_server: Some("prod-*".to_string()),
+                    ..rule("prod-mcp", RequestClass::Mcp)
+                },
+                rule("destructive", RequestClass::Destructive),
+                rule("network", RequestClass::Network),
+                rule("outside", RequestClass::OutsideCwd),
+            ],
+            destructive,
+        )
+        .expect("routes compile");
+
+        let cases = [
+            ("Bash", "rm -rf target", Some("destructive")),
+            ("Bash", "git push --force origin main", Some("destructive")),
+            ("Bash", "psql -c 'DROP TABLE users'", Some("destructive")),
+            ("Bash", "curl -sS https://example.com", Some("network")),
+            ("WebFetch", "https://example.com", Some("network")),
+            ("Write", "../other/file.txt", Some("outside")),
+            ("Write", "src/main.rs", None),
+            ("mcp__prod-db__query", "", Some("prod-mcp")),
+            ("mcp__github__create_issue", "", None),
+            ("Bash", "cargo test", None),
+        ];
+        for (tool, value, expected) in cases {
+            assert_eq!(
+                route_name(&router, tool, value).as_deref(),
+                expected,
+                "{tool} {value}"
+            );
+        }
+    }
+}
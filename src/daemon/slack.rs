@@ -0,0 +1,704 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{Client, Response, multipart};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, OnceCell},
+    time::sleep,
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message};
+use tracing::{info, warn};
+
+use super::transport::{
+    Access, Actor, Button, Inbound, InboundCallback, InboundCommand, InboundMessage, Transport,
+    parse_command,
+};
+use crate::{
+    config::Config,
+    errors::{AppError, Result},
+};
+
+const MAX_SLACK_SECTION_TEXT: usize = 3000;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+const MENTION_COMMANDS: &[&str] = &[
+    "peek",
+    "diff",
+    "log",
+    "sessions",
+    "switch",
+    "grants",
+    "ontimeout",
+];
+
+type SlackSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub struct SlackClient {
+    http: Client,
+    api_url: String,
+    bot_token: SecretString,
+    app_token: SecretString,
+    channel: String,
+    bot_user_id: OnceCell<String>,
+    socket: Mutex<Option<SlackSocket>>,
+}
+
+pub async fn auth_identity(config: &Config) -> Result<String> {
+    let client = SlackClient::from_config(config)?;
+    let auth = client.call("auth.test", json!({})).await?;
+    let user = auth.get("user").and_then(Value::as_str).unwrap_or("bot");
+    let team = auth
+        .get("team")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown team");
+    Ok(format!("{user} in {team}"))
+}
+
+impl SlackClient {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            http: Client::new(),
+            api_url: config.slack_api_url.trim_end_matches('/').to_string(),
+            bot_token: config.slack_bot_token()?,
+            app_token: config.slack_app_token()?,
+            channel: config.slack_channel_id()?.to_string(),
+            bot_user_id: OnceCell::new(),
+            socket: Mutex::new(None),
+        })
+    }
+
+    async fn call(&self, method: &str, body: Value) -> Result<Value> {
+        self.call_with_token(method, &self.bot_token, body).await
+    }
+
+    async fn call_with_token(
+        &self,
+        method: &str,
+        token: &SecretString,
+        body: Value,
+    ) -> Result<Value> {
+        let url = format!("{}/{method}", self.api_url);
+        let mut attempts = 0;
+        loop {
+            let response = self
+                .http
+                .post(&url)
+                .bearer_auth(token.expose_secret())
+                .json(&body)
+                .send()
+                .await?;
+            if response.status().as_u16() == 429 && attempts < MAX_RATE_LIMIT_RETRIES {
+                attempts += 1;
+                let wait = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(1);
+                warn!(
+                    method,
+                    attempt = attempts,
+                    wait_secs = wait,
+                    "slack rate limited"
+                );
+                sleep(Duration::from_secs(wait)).await;
+                continue;
+            }
+            return parse_response(method, response).await;
+        }
+    }
+
+    async fn call_form(&self, method: &str, form: multipart::Form) -> Result<Value> {
+        let response = self
+            .http
+            .post(format!("{}/{method}", self.api_url))
+            .bearer_auth(self.bot_token.expose_secret())
+            .multipart(form)
+            .send()
+            .await?;
+        parse_response(method, response).await
+    }
+
+    async fn post(&self, mut body: Value) -> Result<i64> {
+        body["channel"] = json!(self.channel);
+        body["unfurl_links"] = json!(false);
+        let response = self.call("chat.postMessage", body).await?;
+        response
+            .get("ts")
+            .and_then(Value::as_str)
+            .and_then(ts_to_id)
+            .ok_or_else(|| AppError::SlackApi("chat.postMessage missing ts".to_string()))
+    }
+
+    async fn connect(&self) -> Result<SlackSocket> {
+        self.bot_user_id
+            .get_or_try_init(|| async {
+                let auth = self.call("auth.test", json!({})).await?;
+                Ok::<_, AppError>(
+                    auth.get("user_id")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                )
+            })
+            .await?;
+
+        let opened = self
+            .call_with_token("apps.connections.open", &self.app_token, json!({}))
+            .await?;
+        let Some(url) = opened.get("url").and_then(Value::as_str) else {
+            return Err(AppError::SlackApi(
+                "apps.connections.open missing url".to_string(),
+            ));
+        };
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let (socket, _) = connect_async(url)
+            .await
+            .map_err(|err| AppError::SlackApi(format!("socket mode connect failed: {err}")))?;
+        info!("slack socket mode connected");
+        Ok(socket)
+    }
+}
+
+#[async_trait]
+impl Transport for SlackClient {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn max_text_chars(&self) -> usize {
+        MAX_SLACK_SECTION_TEXT
+    }
+
+    async fn send_text(&self, text: &str) -> Result<i64> {
+        self.post(json!({ "text": escape_mrkdwn(text), "mrkdwn": false }))
+            .await
+    }
+
+    async fn send_markdown(&self, markdown: &str) -> Result<i64> {
+        self.post(json!({ "text": to_mrkdwn(markdown) })).await
+    }
+
+    async fn send_document(
+        &self,
+        file_name: &str,
+        bytes: Vec<u8>,
+        caption: Option<&str>,
+    ) -> Result<i64> {
+        let caption = caption
+            .map(to_mrkdwn)
+            .unwrap_or_else(|| escape_mrkdwn(file_name));
+        let message_id = self.post(json!({ "text": caption })).await?;
+
+        let form = multipart::Form::new()
+            .text("filename", file_name.to_string())
+            .text("length", bytes.len().to_string());
+        let upload = self.call_form("files.getUploadURLExternal", form).await?;
+        let (Some(upload_url), Some(file_id)) = (
+            upload.get("upload_url").and_then(Value::as_str),
+            upload.get("file_id").and_then(Value::as_str),
+        ) else {
+            return Err(AppError::SlackApi(
+                "files.getUploadURLExternal missing upload_url".to_string(),
+            ));
+        };
+        let uploaded = self
+            .http
+            .post(upload_url)
+            .bearer_auth(self.bot_token.expose_secret())
+            .body(bytes)
+            .send()
+            .await?;
+        if !uploaded.status().is_success() {
+            return Err(AppError::SlackApi(format!(
+                "file upload failed with status {}",
+                uploaded.status()
+            )));
+        }
+        self.call(
+            "files.completeUploadExternal",
+            json!({
+                "files": [{ "id": file_id, "title": file_name }],
+                "channel_id": self.channel,
+                "thread_ts": id_to_ts(message_id)
+            }),
+        )
+        .await?;
+        Ok(message_id)
+    }
+
+    async fn edit_text(&self, message_id: i64, text: &str) -> Result<()> {
+        self.call(
+            "chat.update",
+            json!({
+                "channel": self.channel,
+                "ts": id_to_ts(message_id),
+                "text": escape_mrkdwn(text),
+                "blocks": []
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn ask(&self, markdown: &str, buttons: &[Vec<Button>]) -> Result<i64> {
+        let text = to_mrkdwn(markdown);
+        self.post(json!({ "text": text, "blocks": button_blocks(&text, buttons) }))
+            .await
+    }
+
+    async fn edit_ask(
+        &self,
+        message_id: i64,
+        markdown: &str,
+        buttons: &[Vec<Button>],
+    ) -> Result<()> {
+        let text = to_mrkdwn(markdown);
+        self.call(
+            "chat.update",
+            json!({
+                "channel": self.channel,
+                "ts": id_to_ts(message_id),
+                "text": text,
+                "blocks": button_blocks(&text, buttons)
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn ask_reply(&self, markdown: &str, _placeholder: &str) -> Result<i64> {
+        let text = format!("{}\n_Reply in this thread._", to_mrkdwn(markdown));
+        self.post(json!({ "text": text })).await
+    }
+
+    async fn answer_callback(&self, callback_id: &str, notice: Option<&str>) -> Result<()> {
+        let Some(notice) = notice else {
+            return Ok(());
+        };
+        if callback_id.is_empty() {
+            return Ok(());
+        }
+        self.call(
+            "chat.postEphemeral",
+            json!({ "channel": self.channel, "user": callback_id, "text": escape_mrkdwn(notice) }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Vec<Inbound>> {
+        let mut guard = self.socket.lock().await;
+        if guard.is_none() {
+            match self.connect().await {
+                Ok(socket) => *guard = Some(socket),
+                Err(err) => {
+                    warn!(error = %err, "slack socket mode unavailable, retrying");
+                    sleep(RECONNECT_DELAY).await;
+                    return Ok(Vec::new());
+                }
+            }
+        }
+        let Some(socket) = guard.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => {
+                let envelope: SocketEnvelope = serde_json::from_str(&text)?;
+                if let Some(envelope_id) = envelope.envelope_id.as_deref() {
+                    let ack = json!({ "envelope_id": envelope_id }).to_string();
+                    if let Err(err) = socket.send(Message::Text(ack.into())).await {
+                        warn!(error = %err, "failed to acknowledge slack envelope");
+                        *guard = None;
+                        return Ok(Vec::new());
+                    }
+                }
+                if envelope.kind == "disconnect" {
+                    *guard = None;
+                    return Ok(Vec::new());
+                }
+                let bot_user_id = self.bot_user_id.get().map(String::as_str);
+                Ok(translate_envelope(&envelope, &self.channel, bot_user_id)
+                    .into_iter()
+                    .collect())
+            }
+            Some(Ok(Message::Ping(payload))) => {
+                let _ = socket.send(Message::Pong(payload)).await;
+                Ok(Vec::new())
+            }
+            Some(Ok(Message::Close(_))) | None => {
+                *guard = None;
+                Ok(Vec::new())
+            }
+            Some(Ok(_)) => Ok(Vec::new()),
+            Some(Err(err)) => {
+                warn!(error = %err, "slack socket mode connection dropped");
+                *guard = None;
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+async fn parse_response(method: &str, response: Response) -> Result<Value> {
+    let value: Value = response.json().await?;
+    if value.get("ok").and_then(Value::as_bool) != Some(true) {
+        let error = value
+            .get("error")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown_error");
+        return Err(AppError::SlackApi(format!("{method}: {error}")));
+    }
+    Ok(value)
+}
+
+fn button_blocks(text: &str, buttons: &[Vec<Button>]) -> Value {
+    let mut blocks = vec![json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": text.chars().take(MAX_SLACK_SECTION_TEXT).collect::<String>() }
+    })];
+    for (row_index, row) in buttons.iter().enumerate() {
+        let elements = row
+            .iter()
+            .enumerate()
+            .map(|(index, button)| {
+                json!({
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": button.label, "emoji": true },
+                    "value": button.action,
+                    "action_id": format!("codelatch_{row_index}_{index}")
+                })
+            })
+            .collect::<Vec<_>>();
+        blocks.push(json!({ "type": "actions", "elements": elements }));
+    }
+    Value::Array(blocks)
+}
+
+#[derive(Debug, Deserialize)]
+struct SocketEnvelope {
+    envelope_id: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    payload: Value,
+}
+
+fn translate_envelope(
+    envelope: &SocketEnvelope,
+    channel: &str,
+    bot_user_id: Option<&str>,
+) -> Option<Inbound> {
+    let payload = &envelope.payload;
+    let str_at = |pointer: &str| payload.pointer(pointer).and_then(Value::as_str);
+    match envelope.kind.as_str() {
+        "interactive" => {
+            if str_at("/type") != Some("block_actions") {
+                return None;
+            }
+            Some(Inbound::Callback(InboundCallback {
+                id: str_at("/user/id").unwrap_or_default().to_string(),
+                access: channel_access(str_at("/channel/id"), channel),
+                actor: actor(
+                    str_at("/user/id"),
+                    str_at("/user/username").or(str_at("/user/name")),
+                ),
+                chat_id: None,
+                message_id: str_at("/message/ts").and_then(ts_to_id),
+                data: str_at("/actions/0/value")?.to_string(),
+            }))
+        }
+        "slash_commands" => {
+            let command = str_at("/command")?;
+            let args = str_at("/text").unwrap_or_default().trim();
+            Some(Inbound::Command(InboundCommand {
+                name: command.trim_start_matches('/').to_ascii_lowercase(),
+                args: args.to_string(),
+                message: InboundMessage {
+                    access: channel_access(str_at("/channel_id"), channel),
+                    actor: actor(str_at("/user_id"), str_at("/user_name")),
+                    text: format!("{command} {args}").trim_end().to_string(),
+                    chat_id: None,
+                    reply_to: None,
+                },
+            }))
+        }
+        "events_api" => {
+            if payload.pointer("/event/bot_id").is_some()
+                || payload.pointer("/event/subtype").is_some()
+            {
+                return None;
+            }
+            let raw = str_at("/event/text")?;
+            let ts = str_at("/event/ts");
+            let reply_to = str_at("/event/thread_ts")
+                .filter(|thread_ts| Some(*thread_ts) != ts)
+                .and_then(ts_to_id);
+            let access = channel_access(str_at("/event/channel"), channel);
+            let actor = actor(str_at("/event/user"), None);
+            match str_at("/event/type")? {
+                "app_mention" => {
+                    let text = unescape_mrkdwn(strip_mentions(raw).trim());
+                    let first = text.split_whitespace().next().unwrap_or_default();
+                    let message = InboundMessage {
+                        access,
+                        actor,
+                        text: text.clone(),
+                        chat_id: None,
+                        reply_to,
+                    };
+                    if MENTION_COMMANDS.contains(&first.trim_start_matches('/')) {
+                        return Some(parse_command(InboundMessage {
+                            text: format!("/{}", text.trim_start_matches('/')),
+                            ..message
+                        }));
+                    }
+                    Some(Inbound::Message(message))
+                }
+                "message" => {
+                    // Only thread replies are routed; top-level channel chatter and
+                    // mentions (delivered separately as app_mention) are ignored.
+                    reply_to?;
+                    if bot_user_id.is_some_and(|id| raw.contains(&format!("<@{id}>"))) {
+                        return None;
+                    }
+                    Some(parse_command(InboundMessage {
+                        access,
+                        actor,
+                        text: unescape_mrkdwn(raw.trim()),
+                        chat_id: None,
+                        reply_to,
+                    }))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn channel_access(found: Option<&str>, channel: &str) -> Access {
+    if found == Some(channel) {
+        Access::Authorized
+    } else {
+        Access::UnknownChat
+    }
+}
+
+fn actor(id: Option<&str>, name: Option<&str>) -> Option<Actor> {
+    let id = id?;
+    Some(Actor {
+        id: id.to_string(),
+        name: name.unwrap_or(id).to_string(),
+    })
+}
+
+fn strip_mentions(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find('>') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn ts_to_id(ts: &str) -> Option<i64> {
+    let (seconds, fraction) = ts.split_once('.')?;
+    let seconds = seconds.parse::<i64>().ok()?;
+    let micros = format!("{fraction:0<6}").get(..6)?.parse::<i64>().ok()?;
+    Some(seconds * 1_000_000 + micros)
+}
+
+pub fn id_to_ts(id: i64) -> String {
+    format!("{}.{:06}", id / 1_000_000, id % 1_000_000)
+}
+
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape_mrkdwn(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// Outbound markdown is Telegram MarkdownV2: drop its backslash escapes, escape the
+// characters Slack treats as control sequences, and strip code fence languages.
+fn to_mrkdwn(markdown: &str) -> String {
+    let mut unescaped = String::with_capacity(markdown.len());
+    let mut chars = markdown.chars();
+    while let Some(ch) = chars.next() {
+        let ch = if ch == '\\' {
+            chars.next().unwrap_or(ch)
+        } else {
+            ch
+        };
+        unescaped.push(ch);
+    }
+    escape_mrkdwn(&unescaped)
+        .lines()
+        .map(|line| {
+            if line.starts_with("```") && line.len() > 3 && !line[3..].contains('`') {
+                "```"
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex as StdMutex},
+    };
+
+    use axum::{Json, Router, extract::State, routing::post};
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    use super::{SlackClient, id_to_ts, to_mrkdwn, ts_to_id};
+    use crate::{
+        config::{Config, TransportKind},
+        daemon::transport::{Access, Button, Inbound, Transport},
+    };
+
+    #[test]
+    fn slack_ts_round_trips_through_message_ids() {
+        let id = ts_to_id("1712345678.000123").expect("valid ts");
+        assert_eq!(id, 1_712_345_678_000_123);
+        assert_eq!(id_to_ts(id), "1712345678.000123");
+        assert_eq!(ts_to_id("1712345678.5"), Some(1_712_345_678_500_000));
+        assert_eq!(ts_to_id("not-a-ts"), None);
+    }
+
+    #[test]
+    fn markdown_v2_is_translated_to_mrkdwn() {
+        let markdown = "*🔴 Permission* · `api`\n\n```bash\necho a \\> b && c\n```\nDone\\.";
+        assert_eq!(
+            to_mrkdwn(markdown),
+            "*🔴 Permission* · `api`\n\n```\necho a &gt; b &amp;&amp; c\n```\nDone."
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorded {
+        calls: Arc<StdMutex<Vec<(String, Value)>>>,
+    }
+
+    async fn mock_slack(socket_addr: SocketAddr) -> (SocketAddr, Recorded) {
+        let recorded = Recorded::default();
+        let ws_url = format!("ws://{socket_addr}/socket");
+        let app = Router::new()
+            .route(
+                "/{method}",
+                post(
+                    move |State(recorded): State<Recorded>,
+                          axum::extract::Path(method): axum::extract::Path<String>,
+                          body: String| {
+                        let ws_url = ws_url.clone();
+                        async move {
+                            let body = serde_json::from_str(&body).unwrap_or(Value::Null);
+                            recorded.calls.lock().unwrap().push((method.clone(), body));
+                            Json(match method.as_str() {
+                                "auth.test" => json!({"ok": true, "user_id": "UBOT"}),
+                                "apps.connections.open" => json!({"ok": true, "url": ws_url}),
+                                _ => json!({"ok": true, "ts": "1712345678.000100"}),
+                            })
+                        }
+                    },
+                ),
+            )
+            .with_state(recorded.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind http");
+        let addr = listener.local_addr().expect("http addr");
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("serve");
+        });
+        (addr, recorded)
+    }
+
+    #[tokio::test]
+    async fn socket_mode_callbacks_are_acked_and_buttons_posted() {
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await.expect("bind ws");
+        let ws_addr = ws_listener.local_addr().expect("ws addr");
+        let (http_addr, recorded) = mock_slack(ws_addr).await;
+        let ws_server = tokio::spawn(async move {
+            let (stream, _) = ws_listener.accept().await.expect("accept");
+            let mut socket = accept_async(stream).await.expect("handshake");
+            let envelope = json!({
+                "envelope_id": "env-1",
+                "type": "interactive",
+                "payload": {
+                    "type": "block_actions",
+                    "user": {"id": "U1"},
+                    "channel": {"id": "C1"},
+                    "message": {"ts": "1712345678.000100"},
+                    "actions": [{"value": "permit:req-1:allow"}]
+                }
+            });
+            socket
+                .send(Message::Text(envelope.to_string().into()))
+                .await
+                .expect("send envelope");
+            let ack = socket.next().await.expect("ack frame").expect("ack ok");
+            ack.into_text().expect("text ack").to_string()
+        });
+
+        let config = Config {
+            transport: TransportKind::Slack,
+            slack_bot_token: Some("xoxb-test".to_string()),
+            slack_app_token: Some("xapp-test".to_string()),
+            slack_channel_id: Some("C1".to_string()),
+            slack_api_url: format!("http://{http_addr}"),
+            ..Config::default()
+        };
+        let client = SlackClient::from_config(&config).expect("client");
+
+        let inbound = client.receive().await.expect("receive");
+        let Some(Inbound::Callback(callback)) = inbound.into_iter().next() else {
+            panic!("expected callback");
+        };
+        assert_eq!(callback.access, Access::Authorized);
+        assert_eq!(callback.actor.map(|actor| actor.id).as_deref(), Some("U1"));
+        assert_eq!(callback.data, "permit:req-1:allow");
+        assert_eq!(callback.message_id, ts_to_id("1712345678.000100"));
+        let ack: Value = serde_json::from_str(&ws_server.await.expect("ws task")).expect("ack");
+        assert_eq!(ack["envelope_id"], "env-1");
+
+        let buttons = vec![vec![Button::new("Allow", "permit:req-1:allow")]];
+        let id = client.ask("*Run* `ls`", &buttons).await.expect("ask");
+        assert_eq!(id, 1_712_345_678_000_100);
+        let calls = recorded.calls.lock().unwrap().clone();
+        let (_, body) = calls
+            .iter()
+            .find(|(method, _)| method == "chat.postMessage")
+            .expect("postMessage call");
+        assert_eq!(body["channel"], "C1");
+        assert_eq!(
+            body["blocks"][1]["elements"][0]["value"],
+            "permit:req-1:allow"
+        );
+    }
+}
@@ -0,0 +1,648 @@
+use std::{
+    future::Future,
+    num::NonZeroU32,
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use backoff::{ExponentialBackoff, backoff::Backoff};
+use governor::{Quota, RateLimiter};
+use reqwest::{Client, multipart};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::time::sleep;
+use tracing::warn;
+
+use super::transport::{
+    Access, Actor, Button, Inbound, InboundCallback, InboundMessage, Transport, parse_command,
+};
+use crate::errors::{AppError, Result};
+
+const MAX_TELEGRAM_TEXT: usize = 4096;
+
+#[derive(Clone)]
+pub struct TelegramClient {
+    http: Client,
+    api_url: String,
+    token: SecretString,
+    chat_id: i64,
+    authorized_chats: Vec<i64>,
+    authorized_users: Vec<i64>,
+    limiter: Arc<governor::DefaultDirectRateLimiter>,
+    offset: Arc<AtomicI64>,
+}
+
+pub async fn get_bot_username(api_url: &str, token: &str) -> Result<String> {
+    let http = Client::new();
+    let url = format!("{}/bot{token}/getMe", api_url.trim_end_matches('/'));
+    let response: TelegramResponse<BotUser> = http.get(url).send().await?.json().await?;
+    if !response.ok {
+        return Err(AppError::TelegramAuthFailed);
+    }
+    let Some(result) = response.result else {
+        return Err(AppError::TelegramAuthFailed);
+    };
+    Ok(result.username.unwrap_or_else(|| "unknown-bot".to_string()))
+}
+
+pub async fn wait_for_start_chat(api_url: &str, token: &str, max_wait: Duration) -> Result<i64> {
+    let http = Client::new();
+    let mut offset: i64 = 0;
+    let start = tokio::time::Instant::now();
+
+    while start.elapsed() < max_wait {
+        let url = format!("{}/bot{token}/getUpdates", api_url.trim_end_matches('/'));
+        let payload = json!({
+            "timeout": 20,
+            "offset": offset,
+            "allowed_updates": ["message"]
+        });
+        let response: TelegramResponse<Vec<TelegramUpdate>> =
+            http.post(url).json(&payload).send().await?.json().await?;
+
+        if !response.ok {
+            return Err(AppError::TelegramApi(
+                response
+                    .description
+                    .unwrap_or_else(|| "unknown error".to_string()),
+            ));
+        }
+
+        for update in response.result.unwrap_or_default() {
+            offset = update.update_id + 1;
+            if let Some(message) = update.message
+                && message.text.as_deref() == Some("/start")
+            {
+                return Ok(message.chat.id);
+            }
+        }
+    }
+
+    Err(AppError::TelegramPairingTimeout)
+}
+
+impl TelegramClient {
+    pub fn new(
+        api_url: &str,
+        token: SecretString,
+        chat_id: i64,
+        authorized_chats: Vec<i64>,
+        authorized_users: Vec<i64>,
+    ) -> Self {
+        Self {
+            http: Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token,
+            chat_id,
+            authorized_chats,
+            authorized_users,
+            limiter: Arc::new(RateLimiter::direct(Quota::per_second(
+                NonZeroU32::new(20).expect("nonzero"),
+            ))),
+            offset: Arc::new(AtomicI64::new(0)),
+        }
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!(
+            "{}/bot{}/{method}",
+            self.api_url,
+            self.token.expose_secret()
+        )
+    }
+
+    async fn send_message(&self, text: &str) -> Result<i64> {
+        let url = self.method_url("sendMessage");
+        let payload = json!({ "chat_id": self.chat_id, "text": text });
+        let client = self.http.clone();
+
+        self.with_retry(|| {
+            let payload = payload.clone();
+            let url = url.clone();
+            let client = client.clone();
+            async move {
+                let response: TelegramResponse<TelegramSentMessage> = client
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                if !response.ok {
+                    return Err(AppError::TelegramApi(
+                        response
+                            .description
+                            .unwrap_or_else(|| "sendMessage failed".to_string()),
+                    ));
+                }
+                let Some(message) = response.result else {
+                    return Err(AppError::TelegramApi(
+                        "sendMessage missing result".to_string(),
+                    ));
+                };
+                Ok(message.message_id)
+            }
+        })
+        .await
+    }
+
+    async fn send_markdown_with_markup(
+        &self,
+        text: &str,
+        reply_markup: Option<Value>,
+    ) -> Result<i64> {
+        let url = self.method_url("sendMessage");
+        let mut payload = json!({
+            "chat_id": self.chat_id,
+            "text": text,
+            "parse_mode": "MarkdownV2"
+        });
+        if let Some(markup) = reply_markup {
+            payload["reply_markup"] = markup;
+        }
+        let client = self.http.clone();
+
+        self.with_retry(|| {
+            let payload = payload.clone();
+            let url = url.clone();
+            let client = client.clone();
+            async move {
+                let response: TelegramResponse<TelegramSentMessage> = client
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                if !response.ok {
+                    return Err(AppError::TelegramApi(
+                        response
+                            .description
+                            .unwrap_or_else(|| "sendMessage markdown failed".to_string()),
+                    ));
+                }
+                let Some(message) = response.result else {
+                    return Err(AppError::TelegramApi(
+                        "sendMessage markdown missing result".to_string(),
+                    ));
+                };
+                Ok(message.message_id)
+            }
+        })
+        .await
+    }
+
+    async fn send_document(
+        &self,
+        file_name: &str,
+        bytes: Vec<u8>,
+        caption: Option<&str>,
+    ) -> Result<i64> {
+        let url = self.method_url("sendDocument");
+        let file_name = file_name.to_string();
+        let caption = caption.map(str::to_string);
+        let chat_id = self.chat_id.to_string();
+        let document = bytes;
+        let client = self.http.clone();
+
+        self.with_retry(|| {
+            let url = url.clone();
+            let file_name = file_name.clone();
+            let caption = caption.clone();
+            let chat_id = chat_id.clone();
+            let data = document.clone();
+            let client = client.clone();
+            async move {
+                let part = multipart::Part::bytes(data)
+                    .file_name(file_name)
+                    .mime_str("text/plain; charset=utf-8")
+                    .map_err(|err| AppError::TelegramApi(err.to_string()))?;
+                let mut form = multipart::Form::new()
+                    .text("chat_id", chat_id)
+                    .part("document", part);
+                if let Some(caption) = caption {
+                    form = form
+                        .text("caption", caption)
+                        .text("parse_mode", "MarkdownV2".to_string());
+                }
+
+                let response: TelegramResponse<TelegramSentMessage> = client
+                    .post(&url)
+                    .multipart(form)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                if !response.ok {
+                    return Err(AppError::TelegramApi(
+                        response
+                            .description
+                            .unwrap_or_else(|| "sendDocument failed".to_string()),
+                    ));
+                }
+                let Some(message) = response.result else {
+                    return Err(AppError::TelegramApi(
+                        "sendDocument missing result".to_string(),
+                    ));
+                };
+                Ok(message.message_id)
+            }
+        })
+        .await
+    }
+
+    async fn edit_message(&self, message_id: i64, text: &str) -> Result<()> {
+        self.edit_message_payload(json!({
+            "chat_id": self.chat_id,
+            "message_id": message_id,
+            "text": text
+        }))
+        .await
+    }
+
+    async fn edit_message_payload(&self, payload: Value) -> Result<()> {
+        let url = self.method_url("editMessageText");
+        let client = self.http.clone();
+
+        self.with_retry(|| {
+            let payload = payload.clone();
+            let url = url.clone();
+            let client = client.clone();
+            async move {
+                let response: TelegramResponse<Value> = client
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                if !response.ok {
+                    return Err(AppError::TelegramApi(
+                        response
+                            .description
+                            .unwrap_or_else(|| "editMessageText failed".to_string()),
+                    ));
+                }
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    async fn answer_callback_query(
+        &self,
+        callback_query_id: &str,
+        text: Option<&str>,
+    ) -> Result<()> {
+        let url = self.method_url("answerCallbackQuery");
+        let mut payload = json!({ "callback_query_id": callback_query_id });
+        if let Some(text) = text {
+            payload["text"] = json!(text);
+            payload["show_alert"] = json!(true);
+        }
+        let client = self.http.clone();
+
+        self.with_retry(|| {
+            let payload = payload.clone();
+            let url = url.clone();
+            let client = client.clone();
+            async move {
+                let response: TelegramResponse<Value> = client
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                if !response.ok {
+                    return Err(AppError::TelegramApi(
+                        response
+                            .description
+                            .unwrap_or_else(|| "answerCallbackQuery failed".to_string()),
+                    ));
+                }
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    async fn get_updates(&self, offset: i64) -> Result<Vec<TelegramUpdate>> {
+        let url = self.method_url("getUpdates");
+        let payload = json!({
+            "timeout": 20,
+            "offset": offset,
+            "allowed_updates": ["message", "callback_query"]
+        });
+        let client = self.http.clone();
+
+        self.with_retry(|| {
+            let payload = payload.clone();
+            let url = url.clone();
+            let client = client.clone();
+            async move {
+                let response: TelegramResponse<Vec<TelegramUpdate>> = client
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                if !response.ok {
+                    return Err(AppError::TelegramApi(
+                        response
+                            .description
+                            .unwrap_or_else(|| "getUpdates failed".to_string()),
+                    ));
+                }
+                Ok(response.result.unwrap_or_default())
+            }
+        })
+        .await
+    }
+
+    async fn with_retry<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = ExponentialBackoff {
+            initial_interval: Duration::from_millis(250),
+            max_interval: Duration::from_secs(4),
+            max_elapsed_time: Some(Duration::from_secs(20)),
+            ..ExponentialBackoff::default()
+        };
+        backoff.reset();
+
+        let mut attempts: u32 = 0;
+        loop {
+            self.limiter.until_ready().await;
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    attempts += 1;
+                    if !is_retryable_telegram_error(&err) {
+                        return Err(err);
+                    }
+                    let Some(delay) = backoff.next_backoff() else {
+                        return Err(err);
+                    };
+                    warn!(
+                        attempt = attempts,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "retrying telegram request"
+                    );
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+fn inline_keyboard(buttons: &[Vec<Button>]) -> Value {
+    let keyboard = buttons
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|button| json!({"text": button.label, "callback_data": button.action}))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    json!({ "inline_keyboard": keyboard })
+}
+
+fn is_retryable_telegram_error(err: &AppError) -> bool {
+    match err {
+        AppError::Http(http) => {
+            http.is_timeout()
+                || http.is_connect()
+                || http.is_request()
+                || http
+                    .status()
+                    .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+        }
+        AppError::TelegramApi(message) => {
+            let lower = message.to_ascii_lowercase();
+            lower.contains("too many requests")
+                || lower.contains("retry after")
+                || lower.contains("timed out")
+                || lower.contains("bad gateway")
+                || lower.contains("gateway timeout")
+                || lower.contains("internal server error")
+        }
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl Transport for TelegramClient {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn max_text_chars(&self) -> usize {
+        MAX_TELEGRAM_TEXT
+    }
+
+    fn to_chat(&self, chat_id: i64) -> Option<Arc<dyn Transport>> {
+        Some(Arc::new(Self {
+            chat_id,
+            ..self.clone()
+        }))
+    }
+
+    fn chat_id(&self) -> Option<i64> {
+        Some(self.chat_id)
+    }
+
+    async fn send_text(&self, text: &str) -> Result<i64> {
+        self.send_message(text).await
+    }
+
+    async fn send_markdown(&self, markdown: &str) -> Result<i64> {
+        self.send_markdown_with_markup(markdown, None).await
+    }
+
+    async fn send_document(
+        &self,
+        file_name: &str,
+        bytes: Vec<u8>,
+        caption: Option<&str>,
+    ) -> Result<i64> {
+        TelegramClient::send_document(self, file_name, bytes, caption).await
+    }
+
+    async fn edit_text(&self, message_id: i64, text: &str) -> Result<()> {
+        self.edit_message(message_id, text).await
+    }
+
+    async fn ask(&self, markdown: &str, buttons: &[Vec<Button>]) -> Result<i64> {
+        self.send_markdown_with_markup(markdown, Some(inline_keyboard(buttons)))
+            .await
+    }
+
+    async fn edit_ask(
+        &self,
+        message_id: i64,
+        markdown: &str,
+        buttons: &[Vec<Button>],
+    ) -> Result<()> {
+        self.edit_message_payload(json!({
+            "chat_id": self.chat_id,
+            "message_id": message_id,
+            "text": markdown,
+            "parse_mode": "MarkdownV2",
+            "reply_markup": inline_keyboard(buttons)
+        }))
+        .await
+    }
+
+    async fn ask_reply(&self, markdown: &str, placeholder: &str) -> Result<i64> {
+        let markup = json!({
+            "force_reply": true,
+            "input_field_placeholder": placeholder
+        });
+        self.send_markdown_with_markup(markdown, Some(markup)).await
+    }
+
+    async fn answer_callback(&self, callback_id: &str, notice: Option<&str>) -> Result<()> {
+        self.answer_callback_query(callback_id, notice).await
+    }
+
+    async fn receive(&self) -> Result<Vec<Inbound>> {
+        let updates = self.get_updates(self.offset.load(Ordering::SeqCst)).await?;
+        let mut inbound = Vec::with_capacity(updates.len());
+        for update in updates {
+            self.offset.store(update.update_id + 1, Ordering::SeqCst);
+            if let Some(callback) = update.callback_query {
+                let chat_id = callback.message.as_ref().map(|message| message.chat.id);
+                inbound.push(Inbound::Callback(InboundCallback {
+                    id: callback.id,
+                    access: self.access(chat_id, Some(&callback.from)),
+                    actor: Some(callback.from.actor()),
+                    chat_id,
+                    message_id: callback.message.map(|message| message.message_id),
+                    data: callback.data.unwrap_or_default(),
+                }));
+                continue;
+            }
+            if let Some(message) = update.message
+                && let Some(text) = message.text
+            {
+                inbound.push(parse_command(InboundMessage {
+                    access: self.access(Some(message.chat.id), message.from.as_ref()),
+                    actor: message.from.as_ref().map(TelegramUser::actor),
+                    text,
+                    chat_id: Some(message.chat.id),
+                    reply_to: message.reply_to_message.map(|reply| reply.message_id),
+                }));
+            }
+        }
+        Ok(inbound)
+    }
+}
+
+impl TelegramClient {
+    // The paired chat is always authorized. A non-empty user list narrows
+    // every authorized chat, which is what makes a shared group usable.
+    fn access(&self, chat_id: Option<i64>, from: Option<&TelegramUser>) -> Access {
+        let Some(chat_id) = chat_id else {
+            return Access::UnknownChat;
+        };
+        if chat_id != self.chat_id && !self.authorized_chats.contains(&chat_id) {
+            return Access::UnknownChat;
+        }
+        if self.authorized_users.is_empty()
+            || from.is_some_and(|user| self.authorized_users.contains(&user.id))
+        {
+            Access::Authorized
+        } else {
+            Access::UnknownUser
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BotUser {
+    username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramSentMessage {
+    message_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramUpdate {
+    update_id: i64,
+    message: Option<TelegramMessage>,
+    callback_query: Option<TelegramCallbackQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramMessage {
+    message_id: i64,
+    chat: TelegramChat,
+    from: Option<TelegramUser>,
+    text: Option<String>,
+    reply_to_message: Option<TelegramReplyMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramReplyMessage {
+    message_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramCallbackQuery {
+    id: String,
+    from: TelegramUser,
+    data: Option<String>,
+    message: Option<TelegramMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramChat {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramUser {
+    id: i64,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    username: Option<String>,
+}
+
+impl TelegramUser {
+    fn actor(&self) -> Actor {
+        let full_name = [self.first_name.as_deref(), self.last_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name = match (&self.username, full_name.is_empty()) {
+            (Some(username), true) => format!("@{username}"),
+            (Some(username), false) => format!("{full_name} (@{username})"),
+            (None, false) => full_name,
+            (None, true) => self.id.to_string(),
+        };
+        Actor {
+            id: self.id.to_string(),
+            name,
+        }
+    }
+}
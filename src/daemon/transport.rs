@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::errors::Result;
+
+// Outbound markdown uses Telegram MarkdownV2 escaping (see `md_escape_text` and
+// friends); other backends translate it to their own dialect.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn max_text_chars(&self) -> usize;
+
+    // The same bot posting to another chat, for prompts routed to a separate
+    // approver group. Backends bound to a single channel return None.
+    fn to_chat(&self, _chat_id: i64) -> Option<Arc<dyn Transport>> {
+        None
+    }
+
+    // The chat this transport posts to, where message ids are only unique per
+    // chat. Single-channel backends return None.
+    fn chat_id(&self) -> Option<i64> {
+        None
+    }
+
+    async fn send_text(&self, text: &str) -> Result<i64>;
+
+    async fn send_markdown(&self, markdown: &str) -> Result<i64>;
+
+    async fn send_document(
+        &self,
+        file_name: &str,
+        bytes: Vec<u8>,
+        caption: Option<&str>,
+    ) -> Result<i64>;
+
+    async fn edit_text(&self, message_id: i64, text: &str) -> Result<()>;
+
+    async fn ask(&self, markdown: &str, buttons: &[Vec<Button>]) -> Result<i64>;
+
+    async fn edit_ask(
+        &self,
+        message_id: i64,
+        markdown: &str,
+        buttons: &[Vec<Button>],
+    ) -> Result<()>;
+
+    async fn ask_reply(&self, markdown: &str, placeholder: &str) -> Result<i64>;
+
+    async fn answer_callback(&self, callback_id: &str, notice: Option<&str>) -> Result<()>;
+
+    async fn receive(&self) -> Result<Vec<Inbound>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct Button {
+    pub label: String,
+    pub action: String,
+}
+
+impl Button {
+    pub fn new(label: impl Into<String>, action: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            action: action.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Inbound {
+    Command(InboundCommand),
+    Message(InboundMessage),
+    Callback(InboundCallback),
+}
+
+#[derive(Debug, Clone)]
+pub struct InboundCommand {
+    pub name: String,
+    pub args: String,
+    pub message: InboundMessage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Authorized,
+    // Sent from an authorized chat (a group, say) by someone not on the
+    // user allow-list.
+    UnknownUser,
+    UnknownChat,
+}
+
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct InboundMessage {
+    pub access: Access,
+    pub actor: Option<Actor>,
+    pub text: String,
+    pub chat_id: Option<i64>,
+    pub reply_to: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InboundCallback {
+    pub id: String,
+    pub access: Access,
+    pub actor: Option<Actor>,
+    pub chat_id: Option<i64>,
+    pub message_id: Option<i64>,
+    pub data: String,
+}
+
+pub fn parse_command(message: InboundMessage) -> Inbound {
+    let Some(rest) = message.text.strip_prefix('/') else {
+        return Inbound::Message(message);
+    };
+    let (head, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let name = head.split('@').next().unwrap_or_default();
+    if name.is_empty() {
+        return Inbound::Message(message);
+    }
+    Inbound::Command(InboundCommand {
+        name: name.to_ascii_lowercase(),
+        args: args.trim().to_string(),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Access, Inbound, InboundMessage, parse_command};
+
+    fn message(text: &str) -> InboundMessage {
+        InboundMessage {
+            access: Access::Authorized,
+            actor: None,
+            text: text.to_string(),
+            chat_id: None,
+            reply_to: None,
+        }
+    }
+
+    #[test]
+    fn commands_strip_bot_suffix_and_split_args() {
+        let Inbound::Command(command) = parse_command(message("/switch@codelatch_bot  api "))
+        else {
+            panic!("expected command");
+        };
+        assert_eq!(command.name, "switch");
+        assert_eq!(command.args, "api");
+        assert_eq!(command.message.text, "/switch@codelatch_bot  api ");
+    }
+
+    #[test]
+    fn plain_text_is_a_message() {
+        assert!(matches!(
+            parse_command(message("run the tests")),
+            Inbound::Message(_)
+        ));
+        assert!(matches!(parse_command(message("/ ")), Inbound::Message(_)));
+    }
+}
@@ -0,0 +1,328 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use tracing::info;
+
+use crate::errors::{AppError, Result};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    sql: &'static str,
+    // What the migration leaves behind, so a database from before versioning
+    // can be matched to the migrations it already has.
+    probe: Probe,
+}
+
+enum Probe {
+    Table(&'static str),
+    Column(&'static str, &'static str),
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("migrations/0001_initial.sql"),
+        probe: Probe::Table("sessions"),
+    },
+    Migration {
+        version: 2,
+        name: "permission_audit",
+        sql: include_str!("migrations/0002_permission_audit.sql"),
+        probe: Probe::Table("permission_audit"),
+    },
+    Migration {
+        version: 3,
+        name: "permission_grants",
+        sql: include_str!("migrations/0003_permission_grants.sql"),
+        probe: Probe::Table("permission_grants"),
+    },
+    Migration {
+        version: 4,
+        name: "reply_route_requests",
+        sql: include_str!("migrations/0004_reply_route_requests.sql"),
+        probe: Probe::Column("reply_routes", "request_id"),
+    },
+    Migration {
+        version: 5,
+        name: "session_timeout_outcome",
+        sql: include_str!("migrations/0005_session_timeout_outcome.sql"),
+        probe: Probe::Column("sessions", "timeout_outcome"),
+    },
+    Migration {
+        version: 6,
+        name: "audit_decided_by",
+        sql: include_str!("migrations/0006_audit_decided_by.sql"),
+        probe: Probe::Column("permission_audit", "decided_by"),
+    },
+    Migration {
+        version: 7,
+        name: "pending_routes",
+        sql: include_str!("migrations/0007_pending_routes.sql"),
+        probe: Probe::Column("pending_requests", "route"),
+    },
+    Migration {
+        version: 8,
+        name: "session_lifecycle",
+        sql: include_str!("migrations/0008_session_lifecycle.sql"),
+        probe: Probe::Column("sessions", "status"),
+    },
+    Migration {
+        version: 9,
+        name: "pending_audit_fields",
+        sql: include_str!("migrations/0009_pending_audit_fields.sql"),
+        probe: Probe::Column("pending_requests", "tool_name"),
+    },
+    Migration {
+        version: 10,
+        name: "reply_route_chats",
+        sql: include_str!("migrations/0010_reply_route_chats.sql"),
+        probe: Probe::Column("reply_routes", "chat_id"),
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: Option<String>,
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub async fn run(pool: &SqlitePool) -> Result<()> {
+    ensure_version_table(pool).await?;
+    let current = current_version(pool).await?;
+    if current > latest_version() {
+        return Err(AppError::SchemaTooNew {
+            found: current,
+            supported: latest_version(),
+        });
+    }
+    if current == 0 {
+        adopt_unversioned_schema(pool).await?;
+    }
+
+    let current = current_version(pool).await?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        // IMMEDIATE takes the write lock up front, so a CLI migrate and the
+        // daemon cannot both apply a version; whoever waited sees it recorded.
+        let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+        if is_recorded(&mut tx, migration.version).await? {
+            continue;
+        }
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        record(&mut tx, migration).await?;
+        tx.commit().await?;
+        info!(
+            version = migration.version,
+            name = migration.name,
+            "applied database migration"
+        );
+    }
+    Ok(())
+}
+
+// Read-only: a database that was never migrated reports every version as
+// pending instead of gaining a version table.
+pub async fn status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>> {
+    let rows = if has_version_table(pool).await? {
+        sqlx::query(
+            r#"
+        SELECT version, name, strftime('%Y-%m-%dT%H:%M:%SZ', applied_at, 'unixepoch') AS applied_at
+        FROM schema_migrations
+        ORDER BY version ASC
+        "#,
+        )
+        .fetch_all(pool)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    let mut out = Vec::with_capacity(MIGRATIONS.len());
+    for migration in MIGRATIONS {
+        let applied_at = rows
+            .iter()
+            .find(|row| row.try_get::<i64, _>("version").ok() == Some(migration.version))
+            .map(|row| row.try_get::<String, _>("applied_at"))
+            .transpose()?;
+        out.push(MigrationStatus {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied_at,
+        });
+    }
+    for row in &rows {
+        let version = row.try_get::<i64, _>("version")?;
+        if version > latest_version() {
+            out.push(MigrationStatus {
+                version,
+                name: row.try_get::<String, _>("name")?,
+                applied_at: Some(row.try_get::<String, _>("applied_at")?),
+            });
+        }
+    }
+    Ok(out)
+}
+
+pub async fn current_version(pool: &SqlitePool) -> Result<i64> {
+    if !has_version_table(pool).await? {
+        return Ok(0);
+    }
+    let row = sqlx::query("SELECT COALESCE(MAX(version), 0) AS version FROM schema_migrations")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.try_get::<i64, _>("version")?)
+}
+
+async fn has_version_table(pool: &SqlitePool) -> Result<bool> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS count FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row.try_get::<i64, _>("count")? > 0)
+}
+
+async fn is_recorded(conn: &mut SqliteConnection, version: i64) -> Result<bool> {
+    let row = sqlx::query("SELECT COUNT(*) AS count FROM schema_migrations WHERE version = ?1")
+        .bind(version)
+        .fetch_one(conn)
+        .await?;
+    Ok(row.try_get::<i64, _>("count")? > 0)
+}
+
+async fn ensure_version_table(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Databases created before migrations existed were bootstrapped with
+// `CREATE TABLE IF NOT EXISTS` and ad-hoc `ALTER TABLE`s. Each migration whose
+// table or column is already there is recorded as applied; the first one that
+// is missing and everything after it run normally.
+async fn adopt_unversioned_schema(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+    if is_recorded(&mut tx, 1).await? {
+        return Ok(());
+    }
+    for migration in MIGRATIONS {
+        if !probe_applied(&mut tx, &migration.probe).await? {
+            break;
+        }
+        record(&mut tx, migration).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn probe_applied(conn: &mut SqliteConnection, probe: &Probe) -> Result<bool> {
+    let query = match probe {
+        Probe::Table(table) => sqlx::query(
+            "SELECT COUNT(*) AS count FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        )
+        .bind(*table),
+        Probe::Column(table, column) => {
+            sqlx::query("SELECT COUNT(*) AS count FROM pragma_table_info(?1) WHERE name = ?2")
+                .bind(*table)
+                .bind(*column)
+        }
+    };
+    let row = query.fetch_one(conn).await?;
+    Ok(row.try_get::<i64, _>("count")? > 0)
+}
+
+async fn record(conn: &mut SqliteConnection, migration: &Migration) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    sqlx::query("INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)")
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(now)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::{MIGRATIONS, current_version, latest_version, run, status};
+
+    #[test]
+    fn migrations_are_strictly_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert_eq!(pair[1].version, pair[0].version + 1);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[tokio::test]
+    async fn migrations_apply_once_and_refuse_newer_schemas() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("in-memory pool");
+
+        run(&pool).await.expect("fresh migrate");
+        run(&pool).await.expect("idempotent migrate");
+        assert_eq!(
+            current_version(&pool).await.expect("version"),
+            latest_version()
+        );
+
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, 'future', 0)",
+        )
+        .bind(latest_version() + 1)
+        .execute(&pool)
+        .await
+        .expect("insert future version");
+        assert!(run(&pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn unversioned_schemas_adopt_only_what_they_have() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("in-memory pool");
+        for migration in &MIGRATIONS[..2] {
+            sqlx::raw_sql(migration.sql)
+                .execute(&pool)
+                .await
+                .expect("pre-versioning schema");
+        }
+
+        let before = status(&pool).await.expect("status");
+        assert!(before.iter().all(|m| m.applied_at.is_none()));
+        assert_eq!(current_version(&pool).await.expect("version"), 0);
+
+        run(&pool).await.expect("adopting migrate");
+        let after = status(&pool).await.expect("status");
+        assert!(after.iter().all(|m| m.applied_at.is_some()));
+        let grants = sqlx::query("SELECT COUNT(*) FROM permission_grants")
+            .fetch_one(&pool)
+            .await;
+        assert!(grants.is_ok());
+    }
+}
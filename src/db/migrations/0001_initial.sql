@@ -0,0 +1,34 @@
+CREATE TABLE IF NOT EXISTS sessions (
+    session_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    cwd TEXT NOT NULL,
+    tmux_pane TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS pending_requests (
+    request_id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    session_name TEXT NOT NULL,
+    tmux_pane TEXT NOT NULL,
+    hook_event_name TEXT NOT NULL,
+    state TEXT NOT NULL,
+    telegram_message_id INTEGER,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS reply_routes (
+    telegram_message_id INTEGER PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    tmux_pane TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS default_route (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    session_id TEXT NOT NULL,
+    session_name TEXT NOT NULL,
+    tmux_pane TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
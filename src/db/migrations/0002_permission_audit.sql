@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS permission_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    session_name TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    tool_input TEXT NOT NULL,
+    decision TEXT NOT NULL,
+    decider TEXT NOT NULL,
+    latency_ms INTEGER NOT NULL,
+    requested_at INTEGER NOT NULL,
+    decided_at INTEGER NOT NULL
+);
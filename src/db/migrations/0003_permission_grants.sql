@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS permission_grants (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    scope TEXT NOT NULL,
+    scope_value TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    match_kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    request_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
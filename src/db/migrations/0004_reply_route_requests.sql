@@ -0,0 +1 @@
+ALTER TABLE reply_routes ADD COLUMN request_id TEXT;
@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN timeout_outcome TEXT;
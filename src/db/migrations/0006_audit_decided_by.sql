@@ -0,0 +1 @@
+ALTER TABLE permission_audit ADD COLUMN decided_by TEXT;
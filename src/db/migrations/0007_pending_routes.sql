@@ -0,0 +1,4 @@
+ALTER TABLE pending_requests ADD COLUMN route TEXT;
+ALTER TABLE pending_requests ADD COLUMN approver_chat_id INTEGER;
+ALTER TABLE pending_requests ADD COLUMN required_approvals INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE pending_requests ADD COLUMN approvals TEXT;
@@ -0,0 +1,26 @@
+-- last_seen_at was TEXT and sorted lexically; SQLite cannot retype a column,
+-- so the table is rebuilt. Rows from before lifecycle tracking start as idle
+-- and the daemon's pane check sorts out which of them are still alive.
+CREATE TABLE sessions_v8 (
+    session_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    cwd TEXT NOT NULL,
+    tmux_pane TEXT NOT NULL,
+    last_seen_at INTEGER NOT NULL,
+    timeout_outcome TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER,
+    archived_at INTEGER
+);
+
+INSERT INTO sessions_v8
+    (session_id, name, cwd, tmux_pane, last_seen_at, timeout_outcome, status, started_at)
+SELECT session_id, name, cwd, tmux_pane, CAST(last_seen_at AS INTEGER), timeout_outcome,
+       'idle', CAST(last_seen_at AS INTEGER)
+FROM sessions;
+
+DROP TABLE sessions;
+ALTER TABLE sessions_v8 RENAME TO sessions;
+
+CREATE INDEX IF NOT EXISTS idx_sessions_last_seen ON sessions (archived_at, last_seen_at);
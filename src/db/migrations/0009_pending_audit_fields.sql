@@ -0,0 +1,4 @@
+-- Requests abandoned by a daemon restart are audited from the pending row, so
+-- it carries the same tool fields as the audit entry.
+ALTER TABLE pending_requests ADD COLUMN tool_name TEXT NOT NULL DEFAULT 'unknown';
+ALTER TABLE pending_requests ADD COLUMN tool_input TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,21 @@
+-- Telegram message ids are only unique within a chat, so a route is keyed by
+-- both. Routes recorded before the chat was stored keep chat 0 here; the
+-- daemon moves them to the default Telegram chat at startup, and chat-less
+-- transports (Slack) keep looking them up under chat 0.
+CREATE TABLE reply_routes_v10 (
+    chat_id INTEGER NOT NULL DEFAULT 0,
+    telegram_message_id INTEGER NOT NULL,
+    session_id TEXT NOT NULL,
+    tmux_pane TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    request_id TEXT,
+    PRIMARY KEY (chat_id, telegram_message_id)
+);
+
+INSERT INTO reply_routes_v10
+    (telegram_message_id, session_id, tmux_pane, created_at, request_id)
+SELECT telegram_message_id, session_id, tmux_pane, created_at, request_id
+FROM reply_routes;
+
+DROP TABLE reply_routes;
+ALTER TABLE reply_routes_v10 RENAME TO reply_routes;
@@ -1,8 +1,10 @@
+pub mod migrate;
+
 use std::{path::Path, str::FromStr};
 
 use sqlx::{
     Row, SqlitePool,
-    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
 };
 
 use crate::{
@@ -17,13 +19,63 @@ pub struct SessionRecord {
     pub name: String,
     pub cwd: String,
     pub tmux_pane: String,
-    pub last_seen_at: String,
+    pub status: SessionStatus,
+    pub started_at: i64,
+    pub last_seen_at: i64,
+    pub ended_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionStatus {
+    Active,
+    Idle,
+    WaitingOnPermission,
+    Ended,
+    Orphaned,
+}
+
+impl SessionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionStatus::Active => "active",
+            SessionStatus::Idle => "idle",
+            SessionStatus::WaitingOnPermission => "waiting-on-permission",
+            SessionStatus::Ended => "ended",
+            SessionStatus::Orphaned => "orphaned",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "idle" => SessionStatus::Idle,
+            "waiting-on-permission" => SessionStatus::WaitingOnPermission,
+            "ended" => SessionStatus::Ended,
+            "orphaned" => SessionStatus::Orphaned,
+            _ => SessionStatus::Active,
+        }
+    }
+
+    // The status a hook event moves its session to. Notifications fire while
+    // Claude waits on the operator, so they leave the status alone.
+    pub fn after_event(event: &str) -> Option<Self> {
+        match event {
+            "SessionEnd" => Some(SessionStatus::Ended),
+            "Stop" => Some(SessionStatus::Idle),
+            "Notification" => None,
+            _ => Some(SessionStatus::Active),
+        }
+    }
+
+    pub fn is_live(self) -> bool {
+        !matches!(self, SessionStatus::Ended | SessionStatus::Orphaned)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ReplyRoute {
     pub session_id: String,
     pub tmux_pane: String,
+    pub request_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -33,7 +85,80 @@ pub struct DefaultRoute {
     pub tmux_pane: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub request_id: String,
+    pub session_id: String,
+    pub session_name: String,
+    pub tool_name: String,
+    pub tool_input: String,
+    pub decision: String,
+    pub decider: String,
+    pub decided_by: Option<String>,
+    pub latency_ms: i64,
+    pub requested_at: i64,
+    pub decided_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub request_id: String,
+    pub session_id: String,
+    pub session_name: String,
+    pub tool_name: String,
+    pub tool_input: String,
+    pub decision: String,
+    pub decider: String,
+    pub decided_by: Option<String>,
+    pub latency_ms: i64,
+    pub requested_at: String,
+    pub decided_at: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AbandonedRequest {
+    pub request_id: String,
+    pub telegram_message_id: Option<i64>,
+    pub approver_chat_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub session: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub decision: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GrantRecord {
+    pub id: i64,
+    pub scope: String,
+    pub scope_value: String,
+    pub tool_name: String,
+    pub match_kind: String,
+    pub pattern: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewGrant {
+    pub scope: String,
+    pub scope_value: String,
+    pub tool_name: String,
+    pub match_kind: String,
+    pub pattern: String,
+    pub request_id: String,
+}
+
 pub async fn connect(config: &Config) -> Result<SqlitePool> {
+    let pool = open(config).await?;
+    migrate::run(&pool).await?;
+    Ok(pool)
+}
+
+pub async fn open(config: &Config) -> Result<SqlitePool> {
     if let Some(parent) = Path::new(&config.db_path).parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
@@ -46,106 +171,131 @@ pub async fn connect(config: &Config) -> Result<SqlitePool> {
         .max_connections(5)
         .connect_with(options)
         .await?;
-    bootstrap(&pool).await?;
     Ok(pool)
 }
 
-pub async fn bootstrap(pool: &SqlitePool) -> Result<()> {
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS sessions (
-            session_id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            cwd TEXT NOT NULL,
-            tmux_pane TEXT NOT NULL,
-            last_seen_at TEXT NOT NULL
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS pending_requests (
-            request_id TEXT PRIMARY KEY,
-            session_id TEXT NOT NULL,
-            session_name TEXT NOT NULL,
-            tmux_pane TEXT NOT NULL,
-            hook_event_name TEXT NOT NULL,
-            state TEXT NOT NULL,
-            telegram_message_id INTEGER,
-            created_at INTEGER NOT NULL,
-            expires_at INTEGER NOT NULL
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS reply_routes (
-            telegram_message_id INTEGER PRIMARY KEY,
-            session_id TEXT NOT NULL,
-            tmux_pane TEXT NOT NULL,
-            created_at INTEGER NOT NULL
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS default_route (
-            id INTEGER PRIMARY KEY CHECK (id = 1),
-            session_id TEXT NOT NULL,
-            session_name TEXT NOT NULL,
-            tmux_pane TEXT NOT NULL,
-            updated_at INTEGER NOT NULL
-        );
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    Ok(())
+// For inspection only: never creates the file and refuses writes.
+pub async fn open_read_only(config: &Config) -> Result<SqlitePool> {
+    let uri = format!("sqlite://{}", config.db_path);
+    let options = SqliteConnectOptions::from_str(&uri)
+        .map_err(|err| AppError::DbConfig(err.to_string()))?
+        .read_only(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+    Ok(pool)
 }
 
+// `status` of None only refreshes the row. SessionStart begins a new lifetime,
+// and any other status change away from ended/orphaned brings an archived
+// session back.
 pub async fn upsert_session(
     pool: &SqlitePool,
     envelope: &HookEnvelope,
+    status: Option<SessionStatus>,
     now_epoch: i64,
 ) -> Result<()> {
     let pane = envelope.tmux_pane.as_deref().unwrap_or_default();
+    let restart = envelope.hook_event_name == "SessionStart";
     sqlx::query(
         r#"
-        INSERT INTO sessions (session_id, name, cwd, tmux_pane, last_seen_at)
-        VALUES (?1, ?2, ?3, ?4, ?5)
+        INSERT INTO sessions (session_id, name, cwd, tmux_pane, last_seen_at, status, started_at, ended_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, COALESCE(?6, 'active'), ?5, CASE WHEN ?6 = 'ended' THEN ?5 END)
         ON CONFLICT(session_id) DO UPDATE SET
             name = excluded.name,
             cwd = excluded.cwd,
             tmux_pane = excluded.tmux_pane,
-            last_seen_at = excluded.last_seen_at
+            last_seen_at = excluded.last_seen_at,
+            status = COALESCE(?6, sessions.status),
+            started_at = CASE WHEN ?7 THEN excluded.started_at ELSE sessions.started_at END,
+            ended_at = CASE
+                WHEN ?6 IS NULL THEN sessions.ended_at
+                WHEN ?6 = 'ended' THEN excluded.last_seen_at
+            END,
+            archived_at = CASE WHEN ?6 IS NULL OR ?6 = 'ended' THEN sessions.archived_at END
         "#,
     )
     .bind(&envelope.session_id)
     .bind(&envelope.session_name)
     .bind(&envelope.cwd)
     .bind(pane)
-    .bind(now_epoch.to_string())
+    .bind(now_epoch)
+    .bind(status.map(SessionStatus::as_str))
+    .bind(restart)
     .execute(pool)
     .await?;
     Ok(())
 }
 
-pub async fn list_sessions(pool: &SqlitePool) -> Result<Vec<SessionRecord>> {
-    let rows = sqlx::query(
+// A session with a permission request still waiting reports that instead of
+// its stored status, so concurrent prompts never leave a stale marker behind.
+const SESSION_COLUMNS: &str = r#"
+    session_id, name, cwd, tmux_pane, last_seen_at, started_at, ended_at,
+    CASE
+        WHEN status IN ('active', 'idle') AND EXISTS (
+            SELECT 1 FROM pending_requests
+            WHERE pending_requests.session_id = sessions.session_id AND state = 'waiting'
+        ) THEN 'waiting-on-permission'
+        ELSE status
+    END AS status
+"#;
+
+fn session_record(row: &SqliteRow) -> Result<SessionRecord> {
+    Ok(SessionRecord {
+        session_id: row.try_get::<String, _>("session_id")?,
+        name: row.try_get::<String, _>("name")?,
+        cwd: row.try_get::<String, _>("cwd")?,
+        tmux_pane: row.try_get::<String, _>("tmux_pane")?,
+        status: SessionStatus::parse(&row.try_get::<String, _>("status")?),
+        started_at: row.try_get::<i64, _>("started_at")?,
+        last_seen_at: row.try_get::<i64, _>("last_seen_at")?,
+        ended_at: row.try_get::<Option<i64>, _>("ended_at")?,
+    })
+}
+
+pub async fn list_sessions(
+    pool: &SqlitePool,
+    include_archived: bool,
+) -> Result<Vec<SessionRecord>> {
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT session_id, name, cwd, tmux_pane, last_seen_at
+        SELECT {SESSION_COLUMNS}
         FROM sessions
+        WHERE ?1 OR archived_at IS NULL
         ORDER BY last_seen_at DESC
+        "#
+    ))
+    .bind(include_archived)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(session_record).collect()
+}
+
+pub async fn get_session(pool: &SqlitePool, session_id: &str) -> Result<Option<SessionRecord>> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {SESSION_COLUMNS}
+        FROM sessions
+        WHERE session_id = ?1
+        LIMIT 1
+        "#
+    ))
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.as_ref().map(session_record).transpose()
+}
+
+// Sessions whose pane the daemon should keep checking.
+pub async fn live_session_panes(pool: &SqlitePool) -> Result<Vec<(String, String)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT session_id, tmux_pane
+        FROM sessions
+        WHERE status IN ('active', 'idle') AND tmux_pane != ''
         "#,
     )
     .fetch_all(pool)
@@ -153,45 +303,97 @@ pub async fn list_sessions(pool: &SqlitePool) -> Result<Vec<SessionRecord>> {
 
     let mut out = Vec::with_capacity(rows.len());
     for row in rows {
-        out.push(SessionRecord {
-            session_id: row.try_get::<String, _>("session_id")?,
-            name: row.try_get::<String, _>("name")?,
-            cwd: row.try_get::<String, _>("cwd")?,
-            tmux_pane: row.try_get::<String, _>("tmux_pane")?,
-            last_seen_at: row.try_get::<String, _>("last_seen_at")?,
-        });
+        out.push((
+            row.try_get::<String, _>("session_id")?,
+            row.try_get::<String, _>("tmux_pane")?,
+        ));
     }
     Ok(out)
 }
 
-pub async fn get_session(pool: &SqlitePool, session_id: &str) -> Result<Option<SessionRecord>> {
+pub async fn mark_session_orphaned(
+    pool: &SqlitePool,
+    session_id: &str,
+    now_epoch: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions
+        SET status = 'orphaned', ended_at = ?2
+        WHERE session_id = ?1 AND status IN ('active', 'idle')
+        "#,
+    )
+    .bind(session_id)
+    .bind(now_epoch)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn archive_sessions_ended_before(
+    pool: &SqlitePool,
+    before_epoch: i64,
+    now_epoch: i64,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions
+        SET archived_at = ?2
+        WHERE archived_at IS NULL
+          AND status IN ('ended', 'orphaned')
+          AND ended_at <= ?1
+        "#,
+    )
+    .bind(before_epoch)
+    .bind(now_epoch)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_session_timeout_outcome(
+    pool: &SqlitePool,
+    session_id: &str,
+) -> Result<Option<String>> {
     let row = sqlx::query(
         r#"
-        SELECT session_id, name, cwd, tmux_pane, last_seen_at
-        FROM sessions
+        SELECT timeout_outcome FROM sessions
         WHERE session_id = ?1
-        LIMIT 1
         "#,
     )
     .bind(session_id)
     .fetch_optional(pool)
     .await?;
-
     match row {
-        Some(row) => Ok(Some(SessionRecord {
-            session_id: row.try_get::<String, _>("session_id")?,
-            name: row.try_get::<String, _>("name")?,
-            cwd: row.try_get::<String, _>("cwd")?,
-            tmux_pane: row.try_get::<String, _>("tmux_pane")?,
-            last_seen_at: row.try_get::<String, _>("last_seen_at")?,
-        })),
+        Some(row) => Ok(row.try_get::<Option<String>, _>("timeout_outcome")?),
         None => Ok(None),
     }
 }
 
+pub async fn set_session_timeout_outcome(
+    pool: &SqlitePool,
+    session_id: &str,
+    outcome: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET timeout_outcome = ?2
+        WHERE session_id = ?1
+        "#,
+    )
+    .bind(session_id)
+    .bind(outcome)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 pub async fn insert_pending_request(
     pool: &SqlitePool,
     envelope: &HookEnvelope,
+    tool_name: &str,
+    tool_input: &str,
     expires_at: i64,
     now_epoch: i64,
 ) -> Result<()> {
@@ -199,8 +401,9 @@ pub async fn insert_pending_request(
     sqlx::query(
         r#"
         INSERT INTO pending_requests
-        (request_id, session_id, session_name, tmux_pane, hook_event_name, state, created_at, expires_at)
-        VALUES (?1, ?2, ?3, ?4, ?5, 'waiting', ?6, ?7)
+        (request_id, session_id, session_name, tmux_pane, hook_event_name, state, created_at,
+         expires_at, tool_name, tool_input)
+        VALUES (?1, ?2, ?3, ?4, ?5, 'waiting', ?6, ?7, ?8, ?9)
         "#,
     )
     .bind(&envelope.request_id)
@@ -210,6 +413,8 @@ pub async fn insert_pending_request(
     .bind(&envelope.hook_event_name)
     .bind(now_epoch)
     .bind(expires_at)
+    .bind(tool_name)
+    .bind(tool_input)
     .execute(pool)
     .await?;
     Ok(())
@@ -234,6 +439,48 @@ pub async fn set_pending_message_id(
     Ok(())
 }
 
+pub async fn set_pending_route(
+    pool: &SqlitePool,
+    request_id: &str,
+    route: &str,
+    approver_chat_id: Option<i64>,
+    required_approvals: usize,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE pending_requests
+        SET route = ?2, approver_chat_id = ?3, required_approvals = ?4
+        WHERE request_id = ?1
+        "#,
+    )
+    .bind(request_id)
+    .bind(route)
+    .bind(approver_chat_id)
+    .bind(required_approvals as i64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_pending_approvals(
+    pool: &SqlitePool,
+    request_id: &str,
+    approvals: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE pending_requests
+        SET approvals = ?2
+        WHERE request_id = ?1
+        "#,
+    )
+    .bind(request_id)
+    .bind(approvals)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 pub async fn transition_pending_state(
     pool: &SqlitePool,
     request_id: &str,
@@ -253,8 +500,85 @@ pub async fn transition_pending_state(
     Ok(result.rows_affected() > 0)
 }
 
+pub async fn set_pending_expiry(
+    pool: &SqlitePool,
+    request_id: &str,
+    expires_at: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE pending_requests
+        SET expires_at = ?2
+        WHERE request_id = ?1 AND state = 'waiting'
+        "#,
+    )
+    .bind(request_id)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn is_pending_waiting(pool: &SqlitePool, request_id: &str) -> Result<bool> {
+    let row = sqlx::query(
+        r#"
+        SELECT 1 FROM pending_requests
+        WHERE request_id = ?1 AND state = 'waiting'
+        "#,
+    )
+    .bind(request_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+// Abandoned requests were denied when the daemon closed their hook
+// connections, so each one is audited with the daemon_closed decider.
+pub async fn abandon_waiting_requests(
+    pool: &SqlitePool,
+    now_epoch: i64,
+) -> Result<Vec<AbandonedRequest>> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO permission_audit
+        (request_id, session_id, session_name, tool_name, tool_input, decision, decider,
+         latency_ms, requested_at, decided_at)
+        SELECT request_id, session_id, session_name, tool_name, tool_input, 'deny',
+               'daemon_closed', MAX(?1 - created_at, 0) * 1000, created_at, ?1
+        FROM pending_requests
+        WHERE state = 'waiting'
+        "#,
+    )
+    .bind(now_epoch)
+    .execute(&mut *tx)
+    .await?;
+    let rows = sqlx::query(
+        r#"
+        UPDATE pending_requests
+        SET state = 'abandoned'
+        WHERE state = 'waiting'
+        RETURNING request_id, telegram_message_id, approver_chat_id
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    rows.iter()
+        .map(|row| {
+            Ok(AbandonedRequest {
+                request_id: row.try_get::<String, _>("request_id")?,
+                telegram_message_id: row.try_get::<Option<i64>, _>("telegram_message_id")?,
+                approver_chat_id: row.try_get::<Option<i64>, _>("approver_chat_id")?,
+            })
+        })
+        .collect()
+}
+
+// Routes are keyed by chat and message id; chat-less transports use chat 0.
 pub async fn insert_reply_route(
     pool: &SqlitePool,
+    chat_id: Option<i64>,
     telegram_message_id: i64,
     envelope: &HookEnvelope,
     now_epoch: i64,
@@ -265,31 +589,77 @@ pub async fn insert_reply_route(
     sqlx::query(
         r#"
         INSERT OR REPLACE INTO reply_routes
-        (telegram_message_id, session_id, tmux_pane, created_at)
-        VALUES (?1, ?2, ?3, ?4)
+        (chat_id, telegram_message_id, session_id, tmux_pane, created_at)
+        VALUES (?5, ?1, ?2, ?3, ?4)
         "#,
     )
     .bind(telegram_message_id)
     .bind(&envelope.session_id)
     .bind(pane)
     .bind(now_epoch)
+    .bind(chat_id.unwrap_or(0))
     .execute(pool)
     .await?;
     Ok(())
 }
 
+pub async fn insert_permission_note_route(
+    pool: &SqlitePool,
+    chat_id: Option<i64>,
+    telegram_message_id: i64,
+    request_id: &str,
+    now_epoch: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO reply_routes
+        (chat_id, telegram_message_id, session_id, tmux_pane, created_at, request_id)
+        SELECT ?4, ?1, session_id, tmux_pane, ?3, request_id
+        FROM pending_requests
+        WHERE request_id = ?2
+        "#,
+    )
+    .bind(telegram_message_id)
+    .bind(request_id)
+    .bind(now_epoch)
+    .bind(chat_id.unwrap_or(0))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Routes recorded before replies were keyed by chat sit under chat 0. Every
+// one of them came from the default chat, so a transport that has one claims
+// them; where the default chat already has that message id, the newer row wins.
+pub async fn adopt_legacy_reply_routes(pool: &SqlitePool, chat_id: i64) -> Result<u64> {
+    let adopted = sqlx::query(
+        r#"
+        UPDATE OR IGNORE reply_routes
+        SET chat_id = ?1
+        WHERE chat_id = 0
+        "#,
+    )
+    .bind(chat_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(adopted)
+}
+
 pub async fn lookup_reply_route(
     pool: &SqlitePool,
+    chat_id: Option<i64>,
     telegram_message_id: i64,
 ) -> Result<Option<ReplyRoute>> {
     let row = sqlx::query(
         r#"
-        SELECT session_id, tmux_pane
+        SELECT session_id, tmux_pane, request_id
         FROM reply_routes
-        WHERE telegram_message_id = ?1
+        WHERE chat_id = ?2 AND telegram_message_id = ?1
         "#,
     )
     .bind(telegram_message_id)
+    .bind(chat_id.unwrap_or(0))
     .fetch_optional(pool)
     .await?;
 
@@ -297,6 +667,7 @@ pub async fn lookup_reply_route(
         Some(row) => Ok(Some(ReplyRoute {
             session_id: row.try_get::<String, _>("session_id")?,
             tmux_pane: row.try_get::<String, _>("tmux_pane")?,
+            request_id: row.try_get::<Option<String>, _>("request_id")?,
         })),
         None => Ok(None),
     }
@@ -307,7 +678,7 @@ pub async fn find_session_by_name(pool: &SqlitePool, name: &str) -> Result<Optio
         r#"
         SELECT session_id, name, tmux_pane
         FROM sessions
-        WHERE name = ?1
+        WHERE name = ?1 AND status IN ('active', 'idle')
         ORDER BY last_seen_at DESC
         LIMIT 1
         "#,
@@ -371,3 +742,335 @@ pub async fn get_default_route(pool: &SqlitePool) -> Result<Option<DefaultRoute>
         None => Ok(None),
     }
 }
+
+pub async fn insert_audit_entry(pool: &SqlitePool, entry: &NewAuditEntry) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO permission_audit
+        (request_id, session_id, session_name, tool_name, tool_input, decision, decider,
+         decided_by, latency_ms, requested_at, decided_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        "#,
+    )
+    .bind(&entry.request_id)
+    .bind(&entry.session_id)
+    .bind(&entry.session_name)
+    .bind(&entry.tool_name)
+    .bind(&entry.tool_input)
+    .bind(&entry.decision)
+    .bind(&entry.decider)
+    .bind(entry.decided_by.as_deref())
+    .bind(entry.latency_ms)
+    .bind(entry.requested_at)
+    .bind(entry.decided_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_audit_entries(
+    pool: &SqlitePool,
+    filter: &AuditFilter,
+) -> Result<Vec<AuditRecord>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT request_id, session_id, session_name, tool_name, tool_input, decision, decider,
+               decided_by, latency_ms,
+               strftime('%Y-%m-%dT%H:%M:%SZ', requested_at, 'unixepoch') AS requested_at,
+               strftime('%Y-%m-%dT%H:%M:%SZ', decided_at, 'unixepoch') AS decided_at
+        FROM permission_audit
+        WHERE (?1 IS NULL OR session_id = ?1 OR session_name = ?1)
+          AND (?2 IS NULL OR decided_at >= ?2)
+          AND (?3 IS NULL OR decided_at < ?3)
+          AND (?4 IS NULL OR decision = ?4)
+        ORDER BY decided_at DESC, id DESC
+        LIMIT ?5
+        "#,
+    )
+    .bind(filter.session.as_deref())
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(filter.decision.as_deref())
+    .bind(filter.limit.unwrap_or(-1))
+    .fetch_all(pool)
+    .await?;
+
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        out.push(AuditRecord {
+            request_id: row.try_get::<String, _>("request_id")?,
+            session_id: row.try_get::<String, _>("session_id")?,
+            session_name: row.try_get::<String, _>("session_name")?,
+            tool_name: row.try_get::<String, _>("tool_name")?,
+            tool_input: row.try_get::<String, _>("tool_input")?,
+            decision: row.try_get::<String, _>("decision")?,
+            decider: row.try_get::<String, _>("decider")?,
+            decided_by: row.try_get::<Option<String>, _>("decided_by")?,
+            latency_ms: row.try_get::<i64, _>("latency_ms")?,
+            requested_at: row.try_get::<String, _>("requested_at")?,
+            decided_at: row.try_get::<String, _>("decided_at")?,
+        });
+    }
+    Ok(out)
+}
+
+pub async fn parse_timestamp(pool: &SqlitePool, input: &str) -> Result<Option<i64>> {
+    if let Ok(epoch) = input.trim().parse::<i64>() {
+        return Ok(Some(epoch));
+    }
+    let row = sqlx::query("SELECT CAST(strftime('%s', ?1) AS INTEGER) AS epoch")
+        .bind(input.trim())
+        .fetch_one(pool)
+        .await?;
+    Ok(row.try_get::<Option<i64>, _>("epoch")?)
+}
+
+pub async fn insert_grant(pool: &SqlitePool, grant: &NewGrant, now_epoch: i64) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO permission_grants
+        (scope, scope_value, tool_name, match_kind, pattern, request_id, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(&grant.scope)
+    .bind(&grant.scope_value)
+    .bind(&grant.tool_name)
+    .bind(&grant.match_kind)
+    .bind(&grant.pattern)
+    .bind(&grant.request_id)
+    .bind(now_epoch)
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn list_active_grants(pool: &SqlitePool) -> Result<Vec<GrantRecord>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, scope, scope_value, tool_name, match_kind, pattern,
+               strftime('%Y-%m-%dT%H:%M:%SZ', created_at, 'unixepoch') AS created_at
+        FROM permission_grants
+        WHERE revoked_at IS NULL
+        ORDER BY id ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    rows.iter().map(grant_from_row).collect()
+}
+
+pub async fn find_grants_for(
+    pool: &SqlitePool,
+    session_id: &str,
+    cwd: &str,
+    tool_name: &str,
+) -> Result<Vec<GrantRecord>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, scope, scope_value, tool_name, match_kind, pattern,
+               strftime('%Y-%m-%dT%H:%M:%SZ', created_at, 'unixepoch') AS created_at
+        FROM permission_grants
+        WHERE revoked_at IS NULL
+          AND tool_name = ?3
+          AND ((scope = 'session' AND scope_value = ?1) OR (scope = 'repo' AND scope_value = ?2))
+        ORDER BY id ASC
+        "#,
+    )
+    .bind(session_id)
+    .bind(cwd)
+    .bind(tool_name)
+    .fetch_all(pool)
+    .await?;
+    rows.iter().map(grant_from_row).collect()
+}
+
+pub async fn revoke_grant(pool: &SqlitePool, id: i64, now_epoch: i64) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE permission_grants
+        SET revoked_at = ?2
+        WHERE id = ?1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(id)
+    .bind(now_epoch)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn grant_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<GrantRecord> {
+    Ok(GrantRecord {
+        id: row.try_get::<i64, _>("id")?,
+        scope: row.try_get::<String, _>("scope")?,
+        scope_value: row.try_get::<String, _>("scope_value")?,
+        tool_name: row.try_get::<String, _>("tool_name")?,
+        match_kind: row.try_get::<String, _>("match_kind")?,
+        pattern: row.try_get::<String, _>("pattern")?,
+        created_at: row.try_get::<String, _>("created_at")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+
+    use super::{
+        AuditFilter, NewAuditEntry, abandon_waiting_requests, adopt_legacy_reply_routes,
+        insert_audit_entry, insert_pending_request, insert_reply_route, list_audit_entries,
+        lookup_reply_route, migrate,
+    };
+    use crate::models::envelope::HookEnvelope;
+
+    async fn pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("in-memory pool");
+        migrate::run(&pool).await.expect("migrate");
+        pool
+    }
+
+    fn entry(request_id: &str, session: &str, decision: &str, decided_at: i64) -> NewAuditEntry {
+        NewAuditEntry {
+            request_id: request_id.to_string(),
+            session_id: format!("{session}-id"),
+            session_name: session.to_string(),
+            tool_name: "Bash".to_string(),
+            tool_input: "{}".to_string(),
+            decision: decision.to_string(),
+            decider: "operator".to_string(),
+            decided_by: None,
+            latency_ms: 10,
+            requested_at: decided_at,
+            decided_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn audit_filters_combine_session_range_and_decision() {
+        let pool = pool().await;
+        for entry in [
+            entry("r1", "api", "allow", 100),
+            entry("r2", "api", "deny", 200),
+            entry("r3", "web", "deny", 300),
+        ] {
+            insert_audit_entry(&pool, &entry).await.expect("insert");
+        }
+        let ids = |filter: AuditFilter| {
+            let pool = pool.clone();
+            async move {
+                list_audit_entries(&pool, &filter)
+                    .await
+                    .expect("list")
+                    .into_iter()
+                    .map(|record| record.request_id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(ids(AuditFilter::default()).await, ["r3", "r2", "r1"]);
+        let by_session = AuditFilter {
+            session: Some("api-id".to_string()),
+            ..AuditFilter::default()
+        };
+        assert_eq!(ids(by_session).await, ["r2", "r1"]);
+        let range = AuditFilter {
+            since: Some(200),
+            until: Some(300),
+            ..AuditFilter::default()
+        };
+        assert_eq!(ids(range).await, ["r2"]);
+        let denied = AuditFilter {
+            session: Some("web".to_string()),
+            decision: Some("deny".to_string()),
+            ..AuditFilter::default()
+        };
+        assert_eq!(ids(denied).await, ["r3"]);
+        let limited = AuditFilter {
+            limit: Some(1),
+            ..AuditFilter::default()
+        };
+        assert_eq!(ids(limited).await, ["r3"]);
+    }
+
+    fn envelope(tmux_pane: Option<&str>) -> HookEnvelope {
+        HookEnvelope {
+            version: 1,
+            request_id: "r1".to_string(),
+            session_id: "s1".to_string(),
+            session_name: "api".to_string(),
+            tmux_pane: tmux_pane.map(str::to_string),
+            hook_event_name: "PermissionRequest".to_string(),
+            blocking: true,
+            cwd: "/repo".to_string(),
+            payload: json!({}),
+            secrets: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn abandoned_requests_are_audited_as_daemon_closed() {
+        let pool = pool().await;
+        let envelope = envelope(None);
+        insert_pending_request(&pool, &envelope, "Bash", r#"{"command":"ls"}"#, 700, 100)
+            .await
+            .expect("insert pending");
+
+        let abandoned = abandon_waiting_requests(&pool, 130).await.expect("abandon");
+        assert_eq!(abandoned.len(), 1);
+        let audit = list_audit_entries(&pool, &AuditFilter::default())
+            .await
+            .expect("list");
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].decision, "deny");
+        assert_eq!(audit[0].decider, "daemon_closed");
+        assert_eq!(audit[0].tool_input, r#"{"command":"ls"}"#);
+        assert_eq!(audit[0].latency_ms, 30_000);
+
+        assert!(
+            abandon_waiting_requests(&pool, 140)
+                .await
+                .expect("again")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn legacy_reply_routes_move_to_the_default_chat() {
+        let pool = pool().await;
+        let envelope = envelope(Some("%7"));
+        insert_reply_route(&pool, None, 10, &envelope, 100)
+            .await
+            .expect("legacy route");
+        insert_reply_route(&pool, None, 11, &envelope, 100)
+            .await
+            .expect("legacy route");
+        insert_reply_route(&pool, Some(4242), 11, &envelope, 200)
+            .await
+            .expect("current route");
+
+        assert_eq!(
+            adopt_legacy_reply_routes(&pool, 4242).await.expect("adopt"),
+            1
+        );
+        let found = lookup_reply_route(&pool, Some(4242), 10)
+            .await
+            .expect("lookup")
+            .expect("adopted route");
+        assert_eq!(found.tmux_pane, "%7");
+        assert!(
+            lookup_reply_route(&pool, Some(-100), 10)
+                .await
+                .expect("lookup")
+                .is_none()
+        );
+        assert_eq!(
+            adopt_legacy_reply_routes(&pool, 4242).await.expect("again"),
+            0
+        );
+    }
+}
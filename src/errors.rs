@@ -17,6 +17,13 @@ pub enum AppError {
     )]
     ConfigLoad,
 
+    #[error("invalid config: {0}")]
+    #[diagnostic(
+        code(codelatch::config::invalid),
+        help("Fix the value in ~/.config/codelatch/config.toml, then retry.")
+    )]
+    ConfigInvalid(String),
+
     #[error("not configured")]
     #[diagnostic(
         code(codelatch::config::not_configured),
@@ -84,17 +91,91 @@ pub enum AppError {
     #[diagnostic(code(codelatch::telegram::api))]
     TelegramApi(String),
 
+    #[error("slack API error: {0}")]
+    #[diagnostic(
+        code(codelatch::slack::api),
+        help("Check `slack_bot_token`, `slack_app_token` and `slack_channel_id` in config.toml.")
+    )]
+    SlackApi(String),
+
     #[error("invalid sqlite database path/config: {0}")]
     #[diagnostic(code(codelatch::db::config))]
     DbConfig(String),
 
+    #[error("database schema version {found} is newer than this binary supports ({supported})")]
+    #[diagnostic(
+        code(codelatch::db::schema_too_new),
+        help("Upgrade codelatch, or point `db_path` at a different database.")
+    )]
+    SchemaTooNew { found: i64, supported: i64 },
+
+    #[error("invalid policy rule: {0}")]
+    #[diagnostic(
+        code(codelatch::config::policy_rule),
+        help("Fix the `[[policy_rules]]` entry in config.toml, then restart the daemon.")
+    )]
+    PolicyRule(String),
+
+    #[error("invalid gate pattern: {0}")]
+    #[diagnostic(
+        code(codelatch::config::gate),
+        help(
+            "Fix `deny_commands` or `protected_files` under `[gate]` in config.toml, then restart the daemon."
+        )
+    )]
+    GateRule(String),
+
+    #[error("invalid redaction rule: {0}")]
+    #[diagnostic(
+        code(codelatch::config::redaction_rule),
+        help("Fix the `[[redaction.rules]]` entry in config.toml, then restart the daemon.")
+    )]
+    RedactionRule(String),
+
+    #[error("secret store error: {0}")]
+    #[diagnostic(
+        code(codelatch::config::secret_store),
+        help(
+            "Check `secret_store` in config.toml. The `file` store needs CODELATCH_SECRETS_PASSPHRASE; `env` needs CODELATCH_TELEGRAM_BOT_TOKEN."
+        )
+    )]
+    SecretStore(String),
+
+    #[error("invalid timestamp: {0}")]
+    #[diagnostic(
+        code(codelatch::cli::timestamp),
+        help("Use `YYYY-MM-DD`, `YYYY-MM-DD HH:MM:SS` (UTC) or unix seconds.")
+    )]
+    InvalidTimestamp(String),
+
     #[error("failed to serialize config")]
     #[diagnostic(code(codelatch::config::serialize))]
     ConfigSerialize,
 
-    #[error("failed to parse existing Claude settings JSON")]
-    #[diagnostic(code(codelatch::plugin::settings_parse))]
-    PluginSettingsParse,
+    #[error("failed to parse existing Claude settings JSON: {0}")]
+    #[diagnostic(
+        code(codelatch::plugin::settings_parse),
+        help(
+            "Fix the JSON by hand; codelatch will not overwrite a settings file it cannot parse."
+        )
+    )]
+    PluginSettingsParse(String),
+
+    #[error("no project root found above {0}")]
+    #[diagnostic(
+        code(codelatch::plugin::project_root),
+        help("Run this from inside a git repository, or use `--scope user`.")
+    )]
+    ProjectRootNotFound(String),
+
+    #[error("Claude Code plugin install failed: {0}")]
+    #[diagnostic(
+        code(codelatch::plugin::install),
+        help(
+            "Check that the `claude` CLI is on PATH, or pass `--settings` to write hooks into settings.json instead."
+        )
+    )]
+    PluginInstall(String),
 
     #[error("doctor check failed: {0}")]
     #[diagnostic(code(codelatch::doctor::unhealthy))]
@@ -5,6 +5,7 @@ mod db;
 mod errors;
 mod models;
 mod plugin;
+mod secrets;
 
 use miette::Result;
 use tracing_subscriber::EnvFilter;
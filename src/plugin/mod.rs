@@ -1,125 +1,707 @@
-use std::{fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 
-use serde_json::{Value, json};
+use clap::ValueEnum;
+use directories::BaseDirs;
+use serde_json::{Map, Value, json};
 
 use crate::{
-    config,
+    config::{self, Config},
     errors::{AppError, Result},
 };
 
-pub fn install_hooks(binary_path: &Path) -> Result<()> {
-    let settings_path = config::claude_settings_path()?;
+const BACKUP_SUFFIX: &str = "codelatch.bak";
+const PLUGIN_NAME: &str = "codelatch";
+pub const PLUGIN_ID: &str = "codelatch@codelatch";
+const PLUGIN_COMMAND: &str = "${CLAUDE_PLUGIN_ROOT}/scripts/codelatch";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HookScope {
+    #[default]
+    User,
+    Project,
+    Local,
+}
+
+impl HookScope {
+    pub const ALL: [HookScope; 3] = [Self::User, Self::Project, Self::Local];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Project => "project",
+            Self::Local => "local",
+        }
+    }
+
+    pub fn settings_path(self) -> Result<PathBuf> {
+        match self {
+            Self::User => config::claude_settings_path(),
+            Self::Project => Ok(project_root()?.join(".claude").join("settings.json")),
+            Self::Local => Ok(project_root()?.join(".claude").join("settings.local.json")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallMode {
+    Plugin,
+    Settings(HookScope),
+}
+
+impl InstallMode {
+    pub fn label(self) -> String {
+        match self {
+            Self::Plugin => "plugin".to_string(),
+            Self::Settings(scope) => format!("{} scope", scope.label()),
+        }
+    }
+
+    pub fn describe(self) -> Result<String> {
+        Ok(match self {
+            Self::Plugin => format!("plugin {PLUGIN_ID}, {}", plugin_root()?.display()),
+            Self::Settings(scope) => format!(
+                "settings.json, {} scope, {}",
+                scope.label(),
+                scope.settings_path()?.display()
+            ),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Install {
+    pub mode: InstallMode,
+    pub backup: Option<PathBuf>,
+    pub fallback_reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ScopeReport {
+    pub scope: HookScope,
+    pub entries: usize,
+    pub error: Option<String>,
+}
+
+// The plugin route only exists for user scope; project and local installs, and
+// any failure to register through the `claude` CLI, write settings.json instead.
+pub fn install(
+    binary_path: &Path,
+    scope: HookScope,
+    config: &Config,
+    prefer_plugin: bool,
+) -> Result<Install> {
+    let mut fallback_reason = None;
+    if prefer_plugin && scope == HookScope::User {
+        match install_plugin(binary_path, config) {
+            Ok(()) => {
+                uninstall_hooks(HookScope::User)?;
+                return Ok(Install {
+                    mode: InstallMode::Plugin,
+                    backup: None,
+                    fallback_reason: None,
+                });
+            }
+            Err(err) => fallback_reason = Some(err.to_string()),
+        }
+    }
+    let backup = install_hooks(binary_path, scope, config)?;
+    Ok(Install {
+        mode: InstallMode::Settings(scope),
+        backup,
+        fallback_reason,
+    })
+}
+
+pub fn install_plugin(binary_path: &Path, config: &Config) -> Result<()> {
+    let marketplace = write_plugin_artifacts(binary_path, config)?;
+    let marketplace = marketplace.display().to_string();
+    run_claude(&["plugin", "marketplace", "add", &marketplace])?;
+    run_claude(&["plugin", "install", PLUGIN_ID])
+}
+
+pub fn uninstall_plugin() -> Result<()> {
+    run_claude(&["plugin", "uninstall", PLUGIN_ID])?;
+    run_claude(&["plugin", "marketplace", "remove", PLUGIN_NAME])
+}
+
+fn run_claude(args: &[&str]) -> Result<()> {
+    let output = Command::new("claude")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| AppError::PluginInstall(format!("claude CLI unavailable: {err}")))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let detail = if stderr.trim().is_empty() {
+        stdout.trim()
+    } else {
+        stderr.trim()
+    };
+    Err(AppError::PluginInstall(format!(
+        "`claude {}` failed: {detail}",
+        args.join(" ")
+    )))
+}
+
+// Honours CODELATCH_BIN so packaged installs can point hooks at a stable path
+// instead of wherever this process happens to be running from.
+pub fn resolve_binary() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("CODELATCH_BIN").filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    let exe = std::env::current_exe()?;
+    Ok(fs::canonicalize(&exe).unwrap_or(exe))
+}
+
+pub fn marketplace_dir() -> Result<PathBuf> {
+    Ok(config::data_dir()?.join("marketplace"))
+}
+
+fn plugin_root() -> Result<PathBuf> {
+    Ok(marketplace_dir()?.join(PLUGIN_NAME))
+}
+
+pub fn plugin_enabled() -> Result<bool> {
+    let Some(root) = read_settings(&config::claude_settings_path()?)? else {
+        return Ok(false);
+    };
+    Ok(root
+        .get("enabledPlugins")
+        .and_then(|plugins| plugins.get(PLUGIN_ID))
+        .and_then(Value::as_bool)
+        .unwrap_or(false))
+}
+
+pub fn install_modes() -> Result<Vec<InstallMode>> {
+    let mut modes = Vec::new();
+    if plugin_enabled().unwrap_or(false) {
+        modes.push(InstallMode::Plugin);
+    }
+    modes.extend(
+        scope_reports()?
+            .into_iter()
+            .filter(|report| report.entries > 0)
+            .map(|report| InstallMode::Settings(report.scope)),
+    );
+    Ok(modes)
+}
+
+pub fn install_hooks(
+    binary_path: &Path,
+    scope: HookScope,
+    config: &Config,
+) -> Result<Option<PathBuf>> {
+    let settings_path = scope.settings_path()?;
     let Some(parent) = settings_path.parent() else {
         return Err(AppError::HomeDirUnavailable);
     };
     fs::create_dir_all(parent)
         .map_err(|_| AppError::CreateConfigDir(parent.display().to_string()))?;
 
-    let mut root: Value = if settings_path.exists() {
-        let text = fs::read_to_string(&settings_path)?;
-        match serde_json::from_str(&text) {
-            Ok(value) => value,
-            Err(_) => json!({}),
+    let mut root = read_settings(&settings_path)?.unwrap_or_else(|| json!({}));
+    let command = binary_path.display().to_string();
+    merge_hooks(&mut root, build_hooks_json(&command, config));
+    write_settings(&settings_path, &root)
+}
+
+pub fn uninstall_hooks(scope: HookScope) -> Result<usize> {
+    let settings_path = scope.settings_path()?;
+    let Some(mut root) = read_settings(&settings_path)? else {
+        return Ok(0);
+    };
+    let removed = remove_codelatch_hooks(&mut root);
+    if removed > 0 {
+        write_settings(&settings_path, &root)?;
+    }
+    Ok(removed)
+}
+
+pub fn hooks_installed() -> Result<bool> {
+    Ok(!install_modes()?.is_empty())
+}
+
+// Claude Code merges hooks from every scope, so codelatch entries in more than
+// one settings file fire twice and every permission request is sent twice.
+pub fn scope_reports() -> Result<Vec<ScopeReport>> {
+    let mut reports = Vec::with_capacity(HookScope::ALL.len());
+    let mut seen = Vec::new();
+    for scope in HookScope::ALL {
+        // From $HOME the project scope resolves to the user settings file.
+        let path = scope.settings_path()?;
+        if seen.contains(&path) {
+            continue;
         }
-    } else {
-        json!({})
+        seen.push(path.clone());
+        let (entries, error) = match read_settings(&path) {
+            Ok(root) => (root.as_ref().map_or(0, codelatch_entries), None),
+            Err(err) => (0, Some(err.to_string())),
+        };
+        reports.push(ScopeReport {
+            scope,
+            entries,
+            error,
+        });
+    }
+    Ok(reports)
+}
+
+pub fn hook_drift(mode: InstallMode, binary_path: &Path, config: &Config) -> Result<Vec<String>> {
+    let scope = match mode {
+        InstallMode::Plugin => return plugin_drift(binary_path, config),
+        InstallMode::Settings(scope) => scope,
+    };
+    let Some(root) = read_settings(&scope.settings_path()?)? else {
+        return Ok(Vec::new());
     };
+    let command = binary_path.display().to_string();
+    let expected = json!({ "hooks": build_hooks_json(&command, config) });
+    Ok(describe_drift(
+        &codelatch_groups(&root),
+        &codelatch_groups(&expected),
+    ))
+}
 
-    root["hooks"] = build_hooks_json(binary_path);
-    let serialized = serde_json::to_string_pretty(&root)?;
-    fs::write(&settings_path, serialized)
-        .map_err(|_| AppError::WriteConfig(settings_path.display().to_string()))?;
-    Ok(())
+fn plugin_drift(binary_path: &Path, config: &Config) -> Result<Vec<String>> {
+    let root = plugin_root()?;
+    let read_json = |path: PathBuf| -> Value {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or(Value::Null)
+    };
+    let mut drift = Vec::new();
+    let manifest = read_json(root.join(".claude-plugin").join("plugin.json"));
+    if manifest["version"] != env!("CARGO_PKG_VERSION") {
+        drift.push(format!(
+            "plugin version {} is not {}",
+            manifest["version"],
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+    let wrapper = fs::read_to_string(root.join("scripts").join(PLUGIN_NAME)).unwrap_or_default();
+    if wrapper != wrapper_script(binary_path) {
+        drift.push(format!(
+            "scripts/codelatch does not run {}",
+            binary_path.display()
+        ));
+    }
+    let expected = json!({ "hooks": build_hooks_json(PLUGIN_COMMAND, config) });
+    let installed = read_json(root.join("hooks").join("hooks.json"));
+    drift.extend(describe_drift(
+        &codelatch_groups(&installed),
+        &codelatch_groups(&expected),
+    ));
+    Ok(drift)
 }
 
-pub fn hooks_installed() -> Result<bool> {
-    let settings_path = config::claude_settings_path()?;
+fn codelatch_groups(root: &Value) -> BTreeMap<String, Vec<Value>> {
+    let mut out: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let Some(events) = root.get("hooks").and_then(Value::as_object) else {
+        return out;
+    };
+    for (event, groups) in events {
+        for group in groups.as_array().into_iter().flatten() {
+            let hooks = group.get("hooks").and_then(Value::as_array);
+            for hook in hooks
+                .into_iter()
+                .flatten()
+                .filter(|hook| is_codelatch_hook(hook))
+            {
+                out.entry(event.clone()).or_default().push(json!({
+                    "matcher": group.get("matcher"),
+                    "hook": hook
+                }));
+            }
+        }
+    }
+    out
+}
+
+fn describe_drift(
+    installed: &BTreeMap<String, Vec<Value>>,
+    expected: &BTreeMap<String, Vec<Value>>,
+) -> Vec<String> {
+    let mut drift = Vec::new();
+    for (event, groups) in expected {
+        match installed.get(event) {
+            None => drift.push(format!("{event} not installed")),
+            Some(found) if found != groups => drift.push(format!("{event} differs from config")),
+            Some(_) => {}
+        }
+    }
+    for event in installed
+        .keys()
+        .filter(|event| !expected.contains_key(*event))
+    {
+        drift.push(format!("{event} installed but not in hook_events"));
+    }
+    drift
+}
+
+fn project_root() -> Result<PathBuf> {
+    // Writing into the cwd outside any project would scatter `.claude/`
+    // directories wherever the command happened to run.
+    let cwd = std::env::current_dir()?;
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists() || dir.join(".claude").is_dir())
+        .filter(|dir| Some(*dir) != BaseDirs::new().as_ref().map(BaseDirs::home_dir))
+        .map(Path::to_path_buf)
+        .ok_or_else(|| AppError::ProjectRootNotFound(cwd.display().to_string()))
+}
+
+fn backup_path(settings_path: &Path) -> PathBuf {
+    let mut name = settings_path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    name.push(format!(".{BACKUP_SUFFIX}"));
+    settings_path.with_file_name(name)
+}
+
+fn read_settings(settings_path: &Path) -> Result<Option<Value>> {
     if !settings_path.exists() {
-        return Ok(false);
+        return Ok(None);
+    }
+    let text = fs::read_to_string(settings_path)?;
+    if text.trim().is_empty() {
+        return Ok(Some(json!({})));
     }
+    match serde_json::from_str::<Value>(&text) {
+        Ok(value) if value.is_object() => Ok(Some(value)),
+        _ => Err(AppError::PluginSettingsParse(
+            settings_path.display().to_string(),
+        )),
+    }
+}
 
-    let text = fs::read_to_string(&settings_path)?;
-    let parsed: Value = serde_json::from_str(&text).map_err(|_| AppError::PluginSettingsParse)?;
-    Ok(parsed.get("hooks").is_some())
-}
-
-pub fn write_plugin_artifacts(binary_path: &Path) -> Result<()> {
-    let data_dir = config::data_dir()?.join("plugin");
-    fs::create_dir_all(&data_dir)
-        .map_err(|_| AppError::CreateConfigDir(data_dir.display().to_string()))?;
-
-    let plugin_json = json!({
-        "name": "codelatch",
-        "description": "Remote supervision for Claude Code via Telegram",
-        "version": "0.1.0",
-        "author": { "name": "codelatch" }
-    });
-    let hooks_json = json!({
-      "description": "Codelatch remote supervision hooks",
-      "hooks": build_hooks_json(binary_path)
-    });
-
-    fs::write(
-        data_dir.join("plugin.json"),
-        serde_json::to_string_pretty(&plugin_json)?,
-    )
-    .map_err(|_| AppError::WriteConfig(data_dir.join("plugin.json").display().to_string()))?;
-
-    fs::write(
-        data_dir.join("hooks.json"),
-        serde_json::to_string_pretty(&hooks_json)?,
-    )
-    .map_err(|_| AppError::WriteConfig(data_dir.join("hooks.json").display().to_string()))?;
-    Ok(())
-}
-
-fn build_hooks_json(binary_path: &Path) -> Value {
-    let bin = binary_path.display().to_string();
-    json!({
-      "PermissionRequest": [
-        {
-          "matcher": "",
-          "hooks": [
-            { "type": "command", "command": format!("{bin} hook PermissionRequest"), "timeout": 3600 }
-          ]
+fn write_settings(settings_path: &Path, root: &Value) -> Result<Option<PathBuf>> {
+    let serialized = serde_json::to_string_pretty(root)?;
+    let mut backup = None;
+    if settings_path.exists() {
+        if fs::read_to_string(settings_path)? == serialized {
+            return Ok(None);
         }
-      ],
-      "Notification": [
-        {
-          "matcher": "elicitation_dialog",
-          "hooks": [
-            { "type": "command", "command": format!("{bin} hook Notification"), "async": true }
-          ]
+        let path = backup_path(settings_path);
+        fs::copy(settings_path, &path)
+            .map_err(|_| AppError::WriteConfig(path.display().to_string()))?;
+        backup = Some(path);
+    }
+    fs::write(settings_path, serialized)
+        .map_err(|_| AppError::WriteConfig(settings_path.display().to_string()))?;
+    Ok(backup)
+}
+
+// Our entries are recognised by their command (`<path>/codelatch hook <Event>`),
+// so reinstalling from a different binary path still replaces them.
+fn is_codelatch_command(command: &str) -> bool {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    Path::new(program)
+        .file_name()
+        .is_some_and(|name| name == "codelatch")
+        && parts.next() == Some("hook")
+}
+
+fn is_codelatch_hook(hook: &Value) -> bool {
+    hook.get("command")
+        .and_then(Value::as_str)
+        .is_some_and(is_codelatch_command)
+}
+
+fn codelatch_entries(root: &Value) -> usize {
+    let Some(events) = root.get("hooks").and_then(Value::as_object) else {
+        return 0;
+    };
+    events
+        .values()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|group| group.get("hooks").and_then(Value::as_array))
+        .flatten()
+        .filter(|hook| is_codelatch_hook(hook))
+        .count()
+}
+
+fn merge_hooks(root: &mut Value, ours: Value) {
+    remove_codelatch_hooks(root);
+    let Some(root) = root.as_object_mut() else {
+        return;
+    };
+    let hooks = root.entry("hooks").or_insert_with(|| json!({}));
+    if !hooks.is_object() {
+        *hooks = json!({});
+    }
+    let (Some(events), Value::Object(ours)) = (hooks.as_object_mut(), ours) else {
+        return;
+    };
+    for (event, groups) in ours {
+        let entry = events.entry(event).or_insert_with(|| json!([]));
+        if !entry.is_array() {
+            *entry = json!([]);
         }
-      ],
-      "PostToolUseFailure": [
-        {
-          "matcher": "",
-          "hooks": [
-            { "type": "command", "command": format!("{bin} hook PostToolUseFailure"), "async": true }
-          ]
+        if let (Some(existing), Value::Array(groups)) = (entry.as_array_mut(), groups) {
+            existing.extend(groups);
         }
-      ],
-      "Stop": [
-        {
-          "hooks": [
-            { "type": "command", "command": format!("{bin} hook Stop"), "async": true }
-          ]
+    }
+}
+
+fn remove_codelatch_hooks(root: &mut Value) -> usize {
+    let Some(events) = root.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for groups in events.values_mut().filter_map(Value::as_array_mut) {
+        for group in groups.iter_mut() {
+            if let Some(hooks) = group.get_mut("hooks").and_then(Value::as_array_mut) {
+                let before = hooks.len();
+                hooks.retain(|hook| !is_codelatch_hook(hook));
+                removed += before - hooks.len();
+            }
         }
-      ],
-      "SessionStart": [
-        {
-          "hooks": [
-            { "type": "command", "command": format!("{bin} hook SessionStart"), "async": true }
-          ]
+        groups.retain(|group| {
+            group
+                .get("hooks")
+                .and_then(Value::as_array)
+                .is_none_or(|hooks| !hooks.is_empty())
+        });
+    }
+    events.retain(|_, groups| groups.as_array().is_none_or(|groups| !groups.is_empty()));
+    if events.is_empty()
+        && let Some(root) = root.as_object_mut()
+    {
+        root.remove("hooks");
+    }
+    removed
+}
+
+// Lays out a local marketplace that `claude plugin marketplace add` accepts:
+//   marketplace/.claude-plugin/marketplace.json
+//   marketplace/codelatch/{.claude-plugin/plugin.json, hooks/hooks.json, scripts/codelatch}
+// Directory marketplaces load in place, so rewriting these files is enough to
+// update an installed plugin.
+pub fn write_plugin_artifacts(binary_path: &Path, config: &Config) -> Result<PathBuf> {
+    let marketplace = marketplace_dir()?;
+    let root = plugin_root()?;
+    let version = env!("CARGO_PKG_VERSION");
+    let description = "Remote supervision for Claude Code via Telegram";
+
+    write_artifact(
+        &marketplace.join(".claude-plugin").join("marketplace.json"),
+        &serde_json::to_string_pretty(&json!({
+            "name": PLUGIN_NAME,
+            "owner": { "name": "codelatch" },
+            "plugins": [{
+                "name": PLUGIN_NAME,
+                "source": format!("./{PLUGIN_NAME}"),
+                "description": description,
+                "version": version
+            }]
+        }))?,
+    )?;
+    write_artifact(
+        &root.join(".claude-plugin").join("plugin.json"),
+        &serde_json::to_string_pretty(&json!({
+            "name": PLUGIN_NAME,
+            "description": description,
+            "version": version,
+            "author": { "name": "codelatch" }
+        }))?,
+    )?;
+    write_artifact(
+        &root.join("hooks").join("hooks.json"),
+        &serde_json::to_string_pretty(&json!({
+            "description": "Codelatch remote supervision hooks",
+            "hooks": build_hooks_json(PLUGIN_COMMAND, config)
+        }))?,
+    )?;
+    let script = root.join("scripts").join(PLUGIN_NAME);
+    write_artifact(&script, &wrapper_script(binary_path))?;
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+        .map_err(|_| AppError::WriteConfig(script.display().to_string()))?;
+    Ok(marketplace)
+}
+
+fn write_artifact(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|_| AppError::CreateConfigDir(parent.display().to_string()))?;
+    }
+    fs::write(path, contents).map_err(|_| AppError::WriteConfig(path.display().to_string()))
+}
+
+fn wrapper_script(binary_path: &Path) -> String {
+    let bin = binary_path.display().to_string().replace('\'', "'\\''");
+    format!("#!/bin/sh\nexec '{bin}' \"$@\"\n")
+}
+
+fn build_hooks_json(bin: &str, config: &Config) -> Value {
+    let mut hooks = Map::new();
+    for event in config.installed_hook_events() {
+        let mut hook = json!({ "type": "command", "command": format!("{bin} hook {event}") });
+        if config.is_blocking_event(event) {
+            hook["timeout"] = json!(config.hook_timeout_seconds);
+        } else {
+            hook["async"] = json!(true);
         }
-      ],
-      "SessionEnd": [
-        {
-          "hooks": [
-            { "type": "command", "command": format!("{bin} hook SessionEnd"), "async": true }
-          ]
+        let mut group = json!({ "hooks": [hook] });
+        if let Some(matcher) = config.hook_matcher(event) {
+            group["matcher"] = json!(matcher);
         }
-      ]
-    })
+        hooks.insert(event.to_string(), json!([group]));
+    }
+    Value::Object(hooks)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::{
+        PLUGIN_COMMAND, build_hooks_json, codelatch_entries, codelatch_groups, describe_drift,
+        merge_hooks, remove_codelatch_hooks,
+    };
+    use crate::config::Config;
+
+    #[test]
+    fn merge_keeps_foreign_hooks_and_replaces_ours() {
+        let mut root = json!({
+            "model": "opus",
+            "hooks": {
+                "PreToolUse": [
+                    { "matcher": "Bash", "hooks": [{ "type": "command", "command": "./guard.sh" }] }
+                ],
+                "Stop": [
+                    { "hooks": [
+                        { "type": "command", "command": "prettier --write ." },
+                        { "type": "command", "command": "/old/bin/codelatch hook Stop", "async": true }
+                    ] }
+                ]
+            }
+        });
+
+        merge_hooks(
+            &mut root,
+            build_hooks_json("/new/bin/codelatch", &Config::default()),
+        );
+        merge_hooks(
+            &mut root,
+            build_hooks_json("/new/bin/codelatch", &Config::default()),
+        );
+
+        assert_eq!(root["model"], "opus");
+        assert_eq!(
+            root["hooks"]["PreToolUse"][0]["hooks"][0]["command"],
+            "./guard.sh"
+        );
+        let stop = root["hooks"]["Stop"].as_array().expect("stop hooks");
+        assert_eq!(stop.len(), 2);
+        assert_eq!(stop[0]["hooks"][0]["command"], "prettier --write .");
+        assert_eq!(stop[0]["hooks"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            stop[1]["hooks"][0]["command"],
+            "/new/bin/codelatch hook Stop"
+        );
+        assert_eq!(codelatch_entries(&root), 6);
+    }
+
+    #[test]
+    fn uninstall_removes_only_codelatch_entries() {
+        let mut root = json!({
+            "hooks": {
+                "PreToolUse": [
+                    { "matcher": "Bash", "hooks": [{ "type": "command", "command": "./guard.sh" }] }
+                ]
+            }
+        });
+        merge_hooks(
+            &mut root,
+            build_hooks_json("/usr/local/bin/codelatch", &Config::default()),
+        );
+
+        assert_eq!(remove_codelatch_hooks(&mut root), 6);
+        assert_eq!(
+            root,
+            json!({
+                "hooks": {
+                    "PreToolUse": [
+                        { "matcher": "Bash", "hooks": [{ "type": "command", "command": "./guard.sh" }] }
+                    ]
+                }
+            })
+        );
+
+        let mut only_ours = json!({});
+        merge_hooks(
+            &mut only_ours,
+            build_hooks_json("codelatch", &Config::default()),
+        );
+        remove_codelatch_hooks(&mut only_ours);
+        assert_eq!(only_ours, json!({}));
+    }
+
+    #[test]
+    fn hooks_follow_config_and_report_drift() {
+        let mut config = Config {
+            hook_timeout_seconds: 7200,
+            ..Config::default()
+        };
+        config.hook_events.push("PreToolUse".to_string());
+        config
+            .hook_matchers
+            .insert("PreToolUse".to_string(), "Bash|Write".to_string());
+        let bin = "/usr/local/bin/codelatch";
+        let hooks = build_hooks_json(bin, &config);
+        assert_eq!(hooks["PermissionRequest"][0]["hooks"][0]["timeout"], 7200);
+        assert_eq!(hooks["PreToolUse"][0]["matcher"], "Bash|Write");
+        assert_eq!(hooks["PreToolUse"][0]["hooks"][0]["async"], true);
+        assert!(hooks["Stop"][0].get("matcher").is_none());
+
+        let mut gated = Config::default();
+        gated.gate.enabled = true;
+        let gated = build_hooks_json(bin, &gated);
+        assert_eq!(gated["PreToolUse"][0]["hooks"][0]["timeout"], 3600);
+        assert!(gated["PreToolUse"][0]["hooks"][0].get("async").is_none());
+        assert_eq!(
+            gated["PreToolUse"][0]["matcher"],
+            "Bash|Write|Edit|MultiEdit|NotebookEdit"
+        );
+
+        let mut root = json!({});
+        merge_hooks(&mut root, build_hooks_json(bin, &Config::default()));
+        let expected = json!({ "hooks": hooks });
+        let drift = describe_drift(&codelatch_groups(&root), &codelatch_groups(&expected));
+        assert_eq!(
+            drift,
+            vec![
+                "PermissionRequest differs from config".to_string(),
+                "PreToolUse not installed".to_string()
+            ]
+        );
+        assert!(
+            describe_drift(&codelatch_groups(&expected), &codelatch_groups(&expected)).is_empty()
+        );
+    }
+
+    #[test]
+    fn checked_in_plugin_matches_generated_hooks() {
+        let manifest: Value =
+            serde_json::from_str(include_str!("../../plugin/.claude-plugin/plugin.json"))
+                .expect("plugin.json");
+        assert_eq!(manifest["version"], env!("CARGO_PKG_VERSION"));
+        let hooks: Value = serde_json::from_str(include_str!("../../plugin/hooks/hooks.json"))
+            .expect("hooks.json");
+        assert_eq!(
+            hooks["hooks"],
+            build_hooks_json(PLUGIN_COMMAND, &Config::default())
+        );
+    }
 }
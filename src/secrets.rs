@@ -0,0 +1,185 @@
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+use secrecy::SecretString;
+
+use crate::{
+    config::{self, Config, SecretStoreKind},
+    errors::{AppError, Result},
+};
+
+pub const TELEGRAM_BOT_TOKEN: &str = "telegram_bot_token";
+pub const SLACK_BOT_TOKEN: &str = "slack_bot_token";
+pub const SLACK_APP_TOKEN: &str = "slack_app_token";
+
+const KEYRING_SERVICE: &str = "codelatch";
+const PASSPHRASE_ENV: &str = "CODELATCH_SECRETS_PASSPHRASE";
+
+pub fn get(store: SecretStoreKind, name: &str) -> Result<Option<String>> {
+    match store {
+        SecretStoreKind::Keyring => match keyring_entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(AppError::SecretStore(format!("keyring: {err}"))),
+        },
+        SecretStoreKind::File => Ok(FileStore::open()?.read()?.remove(name)),
+        // Env-injected tokens arrive through figment as the plain config field.
+        SecretStoreKind::Config | SecretStoreKind::Env => Ok(None),
+    }
+}
+
+pub fn set(store: SecretStoreKind, name: &str, value: &str) -> Result<()> {
+    match store {
+        SecretStoreKind::Keyring => keyring_entry(name)?
+            .set_password(value)
+            .map_err(|err| AppError::SecretStore(format!("keyring: {err}"))),
+        SecretStoreKind::File => {
+            let file = FileStore::open()?;
+            let mut secrets = file.read()?;
+            secrets.insert(name.to_string(), value.to_string());
+            file.write(&secrets)
+        }
+        SecretStoreKind::Config | SecretStoreKind::Env => Err(AppError::SecretStore(format!(
+            "the `{}` store cannot hold secrets",
+            store.as_str()
+        ))),
+    }
+}
+
+// Moves the plaintext tokens on `config` into `store` and points the config at
+// it. Each value is read back before its plaintext copy is dropped; for `env`
+// that means the variable must already hold it, since nothing else would keep
+// the token once config.toml loses it. The caller saves the config. Returns
+// the names that moved.
+pub fn migrate(config: &mut Config, store: SecretStoreKind) -> Result<Vec<&'static str>> {
+    if store == SecretStoreKind::Config {
+        return Err(AppError::SecretStore(
+            "choose keyring, file or env as the destination".to_string(),
+        ));
+    }
+    let mut moved = Vec::new();
+    for (name, slot) in [
+        (TELEGRAM_BOT_TOKEN, &mut config.telegram_bot_token),
+        (SLACK_BOT_TOKEN, &mut config.slack_bot_token),
+        (SLACK_APP_TOKEN, &mut config.slack_app_token),
+    ] {
+        let Some(value) = slot.clone().filter(|value| !value.trim().is_empty()) else {
+            continue;
+        };
+        if store == SecretStoreKind::Env {
+            let var = env_var(name);
+            if env::var(&var).ok().as_deref() != Some(value.as_str()) {
+                return Err(AppError::SecretStore(format!(
+                    "export {var} with the token from config.toml before moving it to env"
+                )));
+            }
+        } else {
+            set(store, name, &value)?;
+            if get(store, name)?.as_deref() != Some(value.as_str()) {
+                return Err(AppError::SecretStore(format!(
+                    "{name} did not read back from the {} store",
+                    store.as_str()
+                )));
+            }
+        }
+        *slot = None;
+        moved.push(name);
+    }
+    config.secret_store = store;
+    Ok(moved)
+}
+
+// The variable figment reads a token from.
+pub fn env_var(name: &str) -> String {
+    format!("CODELATCH_{}", name.to_uppercase())
+}
+
+pub fn secrets_path() -> Result<PathBuf> {
+    Ok(config::config_path()?.with_file_name("secrets.age"))
+}
+
+fn keyring_entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .map_err(|err| AppError::SecretStore(format!("keyring: {err}")))
+}
+
+// Name/value pairs as TOML, encrypted to a passphrase with age. The passphrase
+// comes from the environment so the daemon can start unattended.
+struct FileStore {
+    path: PathBuf,
+    passphrase: SecretString,
+}
+
+impl FileStore {
+    fn open() -> Result<Self> {
+        let passphrase = env::var(PASSPHRASE_ENV)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| AppError::SecretStore(format!("{PASSPHRASE_ENV} is not set")))?;
+        Ok(Self {
+            path: secrets_path()?,
+            passphrase: passphrase.into(),
+        })
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        let ciphertext = match fs::read(&self.path) {
+            Ok(ciphertext) => ciphertext,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let identity = age::scrypt::Identity::new(self.passphrase.clone());
+        let plaintext = age::decrypt(&identity, &ciphertext)
+            .map_err(|err| AppError::SecretStore(format!("{}: {err}", self.path.display())))?;
+        let text = String::from_utf8(plaintext)
+            .map_err(|err| AppError::SecretStore(format!("{}: {err}", self.path.display())))?;
+        toml::from_str(&text)
+            .map_err(|err| AppError::SecretStore(format!("{}: {err}", self.path.display())))
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let text = toml::to_string(secrets).map_err(|_| AppError::ConfigSerialize)?;
+        let recipient = age::scrypt::Recipient::new(self.passphrase.clone());
+        let ciphertext = age::encrypt(&recipient, text.as_bytes())
+            .map_err(|err| AppError::SecretStore(err.to_string()))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|_| AppError::CreateConfigDir(parent.display().to_string()))?;
+        }
+        fs::write(&self.path, ciphertext)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::FileStore;
+
+    #[test]
+    fn file_store_round_trips_and_rejects_the_wrong_passphrase() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = FileStore {
+            path: dir.path().join("secrets.age"),
+            passphrase: "correct horse".to_string().into(),
+        };
+        assert!(store.read().expect("missing file is empty").is_empty());
+
+        let secrets = BTreeMap::from([("telegram_bot_token".to_string(), "123:abc".to_string())]);
+        store.write(&secrets).expect("write");
+        let raw = std::fs::read(&store.path).expect("raw file");
+        assert!(!String::from_utf8_lossy(&raw).contains("123:abc"));
+        assert_eq!(store.read().expect("read"), secrets);
+
+        let wrong = FileStore {
+            path: store.path.clone(),
+            passphrase: "battery staple".to_string().into(),
+        };
+        assert!(wrong.read().is_err());
+    }
+}
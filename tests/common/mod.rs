@@ -0,0 +1,464 @@
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Path as UrlPath, State},
+    routing::post,
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, UnixStream},
+    process::{Child, Command},
+    sync::Notify,
+    time::{Instant, sleep, timeout},
+};
+
+pub const CHAT_ID: i64 = 4242;
+pub const USER_ID: i64 = 7001;
+pub const SESSION_ID: &str = "01TESTSESSION";
+pub const SESSION_NAME: &str = "api";
+pub const PANE: &str = "%7";
+
+const WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub method: String,
+    pub body: Value,
+    pub message_id: i64,
+}
+
+#[derive(Default)]
+struct MockState {
+    calls: Mutex<Vec<Call>>,
+    updates: Mutex<VecDeque<Value>>,
+    notify: Notify,
+    next_message_id: AtomicI64,
+    next_update_id: AtomicI64,
+}
+
+#[derive(Clone)]
+pub struct MockTelegram {
+    pub addr: SocketAddr,
+    state: Arc<MockState>,
+}
+
+impl MockTelegram {
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState {
+            next_message_id: AtomicI64::new(100),
+            next_update_id: AtomicI64::new(1),
+            ..MockState::default()
+        });
+        let app = Router::new()
+            .route("/{bot}/{method}", post(handle_method))
+            .with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock");
+        let addr = listener.local_addr().expect("mock addr");
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("serve mock");
+        });
+        Self { addr, state }
+    }
+
+    pub fn calls(&self, method: &str) -> Vec<Call> {
+        self.state
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| call.method == method)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn wait_for(&self, method: &str, predicate: impl Fn(&Value) -> bool) -> Call {
+        self.wait_for_call(method, |call| predicate(&call.body))
+            .await
+    }
+
+    pub async fn wait_for_call(&self, method: &str, predicate: impl Fn(&Call) -> bool) -> Call {
+        let deadline = Instant::now() + WAIT;
+        loop {
+            if let Some(call) = self.calls(method).into_iter().find(|call| predicate(call)) {
+                return call;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for {method}; saw {:#?}",
+                self.state.calls.lock().unwrap()
+            );
+            sleep(Duration::from_millis(25)).await;
+        }
+    }
+
+    pub async fn wait_for_text(&self, method: &str, needle: &str) -> Call {
+        self.wait_for(method, |body| text_of(body).contains(needle))
+            .await
+    }
+
+    pub fn push_message(&self, chat_id: i64, text: &str, reply_to: Option<i64>) {
+        self.push_message_from(chat_id, USER_ID, text, reply_to);
+    }
+
+    pub fn push_message_from(&self, chat_id: i64, user_id: i64, text: &str, reply_to: Option<i64>) {
+        let mut message = json!({
+            "message_id": self.state.next_message_id.fetch_add(1, Ordering::SeqCst),
+            "chat": { "id": chat_id },
+            "from": user(user_id),
+            "text": text
+        });
+        if let Some(reply_to) = reply_to {
+            message["reply_to_message"] = json!({ "message_id": reply_to });
+        }
+        self.push_update(json!({ "message": message }));
+    }
+
+    pub fn push_callback(&self, chat_id: i64, message_id: i64, data: &str) -> String {
+        self.push_callback_from(chat_id, USER_ID, message_id, data)
+    }
+
+    pub fn push_callback_from(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+        message_id: i64,
+        data: &str,
+    ) -> String {
+        let id = format!("cb-{}", self.state.next_update_id.load(Ordering::SeqCst));
+        self.push_update(json!({
+            "callback_query": {
+                "id": id,
+                "from": user(user_id),
+                "data": data,
+                "message": { "message_id": message_id, "chat": { "id": chat_id } }
+            }
+        }));
+        id
+    }
+
+    fn push_update(&self, mut update: Value) {
+        update["update_id"] = json!(self.state.next_update_id.fetch_add(1, Ordering::SeqCst));
+        self.state.updates.lock().unwrap().push_back(update);
+        self.state.notify.notify_one();
+    }
+}
+
+fn user(id: i64) -> Value {
+    if id == USER_ID {
+        json!({ "id": id, "is_bot": false, "first_name": "Ada", "username": "ada" })
+    } else {
+        json!({ "id": id, "is_bot": false, "first_name": "Mallory" })
+    }
+}
+
+async fn handle_method(
+    State(state): State<Arc<MockState>>,
+    UrlPath((_bot, method)): UrlPath<(String, String)>,
+    body: Bytes,
+) -> Json<Value> {
+    let parsed = serde_json::from_slice(&body)
+        .unwrap_or_else(|_| json!({ "raw": String::from_utf8_lossy(&body) }));
+    if method == "getUpdates" {
+        if state.updates.lock().unwrap().is_empty() {
+            let _ = timeout(Duration::from_millis(500), state.notify.notified()).await;
+        }
+        let updates = state.updates.lock().unwrap().drain(..).collect::<Vec<_>>();
+        return Json(json!({ "ok": true, "result": updates }));
+    }
+
+    let message_id = state.next_message_id.fetch_add(1, Ordering::SeqCst);
+    state.calls.lock().unwrap().push(Call {
+        method: method.clone(),
+        body: parsed,
+        message_id,
+    });
+    let result = match method.as_str() {
+        "getMe" => json!({ "username": "codelatch_test_bot" }),
+        "sendMessage" | "sendDocument" => json!({ "message_id": message_id }),
+        _ => json!(true),
+    };
+    Json(json!({ "ok": true, "result": result }))
+}
+
+pub fn text_of(body: &Value) -> String {
+    body.get("text")
+        .or_else(|| body.get("raw"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+pub fn callback_data(call: &Call, label: &str) -> String {
+    call.body["reply_markup"]["inline_keyboard"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|row| row.as_array().cloned().unwrap_or_default())
+        .find(|button| button["text"] == label)
+        .and_then(|button| button["callback_data"].as_str().map(str::to_string))
+        .unwrap_or_else(|| panic!("no {label} button in {:#}", call.body))
+}
+
+pub struct Harness {
+    pub mock: MockTelegram,
+    pub dir: TempDir,
+    pub workdir: PathBuf,
+    daemon: Child,
+}
+
+impl Harness {
+    pub async fn start() -> Self {
+        Self::start_with(600, "").await
+    }
+
+    pub async fn start_with(auto_deny_seconds: u64, extra_config: &str) -> Self {
+        let mock = MockTelegram::start().await;
+        let dir = tempfile::tempdir().expect("tempdir");
+        let workdir = dir.path().join("repo");
+        std::fs::create_dir_all(&workdir).expect("workdir");
+        write_config(dir.path(), &mock, auto_deny_seconds, extra_config);
+        write_tmux_shim(dir.path());
+
+        let daemon = spawn_daemon(dir.path());
+        let harness = Self {
+            mock,
+            dir,
+            workdir,
+            daemon,
+        };
+        harness.wait_for_socket().await;
+        harness
+    }
+
+    pub fn home(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        self.home().join("codelatch.sock")
+    }
+
+    async fn wait_for_socket(&self) {
+        let deadline = Instant::now() + WAIT;
+        while UnixStream::connect(self.socket_path()).await.is_err() {
+            assert!(Instant::now() < deadline, "daemon did not start");
+            sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    pub fn hook(&self, event: &str, payload: Value) -> HookRun {
+        self.hook_with_env(event, payload, &[])
+    }
+
+    pub fn hook_with_env(&self, event: &str, payload: Value, vars: &[(&str, &str)]) -> HookRun {
+        let mut command = command(self.home());
+        command
+            .env("CODELATCH_SESSION_ID", SESSION_ID)
+            .env("CODELATCH_SESSION_NAME", SESSION_NAME)
+            .envs(vars.iter().copied());
+        self.spawn_hook(command, event, payload)
+    }
+
+    // A Claude session that was not started through codelatch.
+    pub fn unmanaged_hook(&self, event: &str, payload: Value) -> HookRun {
+        self.spawn_hook(command(self.home()), event, payload)
+    }
+
+    fn spawn_hook(&self, mut command: Command, event: &str, payload: Value) -> HookRun {
+        let mut child = command
+            .args(["hook", event])
+            .current_dir(&self.workdir)
+            .env("TMUX_PANE", PANE)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("spawn hook");
+        let mut stdin = child.stdin.take().expect("hook stdin");
+        let payload = payload.to_string();
+        tokio::spawn(async move {
+            let _ = stdin.write_all(payload.as_bytes()).await;
+        });
+        HookRun { child }
+    }
+
+    pub fn set_pane_output(&self, text: &str) {
+        std::fs::write(self.home().join("pane.txt"), text).expect("write pane output");
+    }
+
+    pub fn kill_pane(&self) {
+        std::fs::write(self.home().join("dead-panes"), format!("{PANE}\n"))
+            .expect("write dead panes");
+    }
+
+    pub fn revive_pane(&self) {
+        let _ = std::fs::remove_file(self.home().join("dead-panes"));
+    }
+
+    pub fn set_tmux_down(&self, down: bool) {
+        let path = self.home().join("tmux-down");
+        if down {
+            std::fs::write(path, "").expect("write tmux-down");
+        } else {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    pub fn tmux_log(&self) -> String {
+        std::fs::read_to_string(self.home().join("tmux.log")).unwrap_or_default()
+    }
+
+    pub async fn wait_for_tmux(&self, needle: &str) {
+        let deadline = Instant::now() + WAIT;
+        while !self.tmux_log().contains(needle) {
+            assert!(
+                Instant::now() < deadline,
+                "tmux never saw {needle:?}; log:\n{}",
+                self.tmux_log()
+            );
+            sleep(Duration::from_millis(25)).await;
+        }
+    }
+
+    pub async fn kill_daemon(&mut self) {
+        let _ = self.daemon.start_kill();
+        let _ = self.daemon.wait().await;
+    }
+
+    pub async fn restart_daemon(&mut self) {
+        self.kill_daemon().await;
+        self.daemon = spawn_daemon(self.home());
+        self.wait_for_socket().await;
+    }
+
+    pub async fn stop(mut self) -> TempDir {
+        self.kill_daemon().await;
+        self.dir
+    }
+}
+
+pub struct HookRun {
+    child: Child,
+}
+
+impl HookRun {
+    pub async fn output(self) -> Output {
+        timeout(WAIT, self.child.wait_with_output())
+            .await
+            .expect("hook finished in time")
+            .expect("hook output")
+    }
+
+    pub async fn json(self) -> Value {
+        let output = self.output().await;
+        assert!(
+            output.status.success(),
+            "hook failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).expect("hook json")
+    }
+
+    pub async fn decision(self) -> Value {
+        self.json().await["hookSpecificOutput"]["decision"].clone()
+    }
+}
+
+pub fn bash(command: &str) -> Value {
+    json!({ "tool_name": "Bash", "tool_input": { "command": command } })
+}
+
+fn spawn_daemon(home: &Path) -> Child {
+    command(home)
+        .args(["start", "--foreground"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("spawn daemon")
+}
+
+pub fn command(home: &Path) -> Command {
+    let path = format!(
+        "{}:{}",
+        home.join("bin").display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let mut command = Command::new(env!("CARGO_BIN_EXE_codelatch"));
+    command
+        .env_clear()
+        .env("HOME", home)
+        .env("PATH", path)
+        .env("RUST_LOG", "off");
+    command
+}
+
+fn write_config(home: &Path, mock: &MockTelegram, auto_deny_seconds: u64, extra: &str) {
+    let config_dir = home.join(".config").join("codelatch");
+    std::fs::create_dir_all(&config_dir).expect("config dir");
+    let config = format!(
+        r#"telegram_bot_token = "123:test"
+telegram_chat_id = {CHAT_ID}
+telegram_api_url = "http://{addr}"
+auto_deny_seconds = {auto_deny_seconds}
+socket_path = "{socket}"
+db_path = "{db}"
+{extra}
+"#,
+        addr = mock.addr,
+        socket = home.join("codelatch.sock").display(),
+        db = home.join("codelatch.db").display(),
+    );
+    std::fs::write(config_dir.join("config.toml"), config).expect("write config");
+}
+
+// Stands in for tmux: records every invocation and serves `capture-pane` from
+// pane.txt so commands that read or drive the pane are observable. Panes listed
+// in dead-panes fail the daemon's liveness probe, and while tmux-down exists
+// every probe fails the way it does with no tmux server running.
+fn write_tmux_shim(home: &Path) {
+    let bin = home.join("bin");
+    std::fs::create_dir_all(&bin).expect("bin dir");
+    let script = format!(
+        r#"#!/bin/sh
+printf '%s\n' "$*" >> "{log}"
+case "$1" in
+  capture-pane) cat "{pane}" 2>/dev/null ;;
+  display-message)
+    case "$*" in
+      *pane_id*)
+        [ -e "{down}" ] && {{ echo "no server running on /tmp/tmux-0/default" >&2; exit 1; }}
+        grep -qxF -- "$4" "{dead}" 2>/dev/null && {{ echo "can't find pane: $4" >&2; exit 1; }}
+        echo "$4" ;;
+      *pane_pid*) echo 999999 ;;
+      *) echo zsh ;;
+    esac ;;
+esac
+exit 0
+"#,
+        log = home.join("tmux.log").display(),
+        pane = home.join("pane.txt").display(),
+        dead = home.join("dead-panes").display(),
+        down = home.join("tmux-down").display(),
+    );
+    let path = bin.join("tmux");
+    std::fs::write(&path, script).expect("write tmux shim");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).expect("chmod shim");
+}